@@ -9,15 +9,39 @@ use crate::AppState;
 pub fn get_router(state: &AppState) -> Router {
     Router::new()
         .route("/apply", post(routes::apply_rule))
+        .route("/apply/forward", post(routes::apply_forward))
         .route("/exercise", get(routes::get_exercises))
         .route("/exercise/{id}", get(routes::get_exercise))
+        .route("/exercise/{id}/latex", get(routes::get_exercise_latex))
+        .route(
+            "/exercise/{id}/solution",
+            get(routes::get_exercise_solution),
+        )
+        .route(
+            "/exercise/{id}/hints/dot",
+            get(routes::get_exercise_hints_dot),
+        )
         .route("/exercise", post(routes::create_exercise))
         .route("/parse", post(routes::parse))
         .route("/rules", get(routes::all_rules))
+        .route("/rules/applicable", post(routes::applicable_rules))
         .route("/check", post(routes::check))
         .route("/statement/hint", post(routes::get_tipp))
+        .route("/statement/prove", post(routes::prove))
         .route("/add_tree", post(routes::add_tree))
+        .route("/tree/latex", post(routes::tree_latex))
+        .route("/tree/dot", post(routes::tree_dot))
+        .route("/tree/validate", post(routes::validate))
+        .route("/tree/merge", post(routes::merge_tree))
         .route("/exercise/{id}/feedback", post(routes::post_feedback))
+        .route("/session", post(routes::create_session))
+        .route("/session/{id}", get(routes::get_session))
+        .route("/session/{id}/goals", get(routes::session_goals))
+        .route("/session/{id}/apply", post(routes::session_apply))
+        .route("/session/{id}/undo", post(routes::session_undo))
+        .route("/session/{id}/redo", post(routes::session_redo))
+        .route("/session/{id}/complete", post(routes::complete_session))
+        .route("/admin/hints/check", get(routes::check_hints))
         // .route("/tree", post(routes::add_tree))
         .with_state(state.clone())
 }
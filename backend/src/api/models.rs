@@ -5,8 +5,12 @@ use uuid::Uuid;
 use utoipa::IntoParams;
 
 use crate::lib::derivation::formula::Identifier;
+use crate::lib::derivation::sat::{CounterModel, Verdict};
+use crate::lib::derivation::signature::Signature;
 use crate::lib::derivation::{formula::Formula, statement::Statement};
-use crate::lib::rule::Rules;
+use crate::lib::latex::LatexStyle;
+use crate::lib::rule::{RuleIdentifier, Rules};
+use crate::lib::session::Goal;
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord)]
 
@@ -23,6 +27,11 @@ pub struct Pair {
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord)]
 pub struct CreateExerciseRequest {
     pub statement: Statement,
+    /// Classical exercises may be solved with the classical rules (RAA, LEM, ¬¬E).
+    #[serde(default)]
+    pub classical: bool,
+    /// Time limit for the validity check, capped by the server.
+    pub timeout_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -33,6 +42,7 @@ pub struct Exercise {
     pub dislikes: i32,
     pub difficulty: f64,
     pub hint: bool,
+    pub classical: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -69,6 +79,43 @@ pub struct ApplyRuleParams {
     pub substitution: Vec<ElementMapping>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ApplicableRulesParams {
+    pub statement: Statement,
+    #[serde(default)]
+    pub classical: bool,
+}
+
+/// The result of applying a rule backwards. The premisses are only known once every
+/// identifier of the rule is mapped.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AppliedRule {
+    pub rule: Rules,
+    pub premisses: Vec<Statement>,
+    pub mapping: Vec<FormulaMapping>,
+    pub substitution: Vec<ElementMapping>,
+    pub unbound: Vec<RuleIdentifier>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ForwardRuleParams {
+    /// Already derived statements, in the order of the premisses of the rule.
+    pub premisses: Vec<Statement>,
+    pub rule: Rules,
+    pub mapping: Vec<FormulaMapping>,
+    pub substitution: Vec<ElementMapping>,
+    /// The assumptions of the conclusion, only used by rules without premisses.
+    pub context: Option<Vec<Formula>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MergeTreeRequest {
+    pub nodes: Vec<Node>,
+    pub root_id: Uuid,
+    /// Goals without a rule, each is closed by a node of another fragment.
+    pub goals: Vec<Goal>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
 pub struct Feedback {
     pub like: bool,
@@ -80,8 +127,140 @@ pub struct ParseParams {
     pub formula: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ParsedFormula {
+    pub formula: Formula,
+    pub signature: Signature,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
 pub struct Tipp {
     pub rule: Rules,
     pub premisses: Vec<Statement>,
+    /// A derivation of the statement starting with the rule, only given for the level
+    /// `Tree`.
+    pub tree: Option<CreateTreeRequest>,
+    /// How often the derivation was submitted.
+    pub uses: u32,
+    /// The number of rules of the shortest known derivation starting with the rule.
+    pub size: u32,
+}
+
+/// How much a hint gives away.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, ToSchema, Default, PartialEq, Eq)]
+pub enum HintLevel {
+    Rule,
+    #[default]
+    Premisses,
+    Tree,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct HintParams {
+    #[serde(flatten)]
+    pub statement: Statement,
+    #[serde(default)]
+    pub level: HintLevel,
+    #[serde(default)]
+    pub classical: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
+pub struct ProveParams {
+    pub statement: Statement,
+    pub max_depth: Option<u32>,
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub classical: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ProofSearchResult {
+    pub found: bool,
+    pub tree: Option<CreateTreeRequest>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CheckResult {
+    pub valid: bool,
+    pub verdict: Verdict,
+    /// Why the solver could not decide, e.g. a timeout.
+    pub reason: Option<String>,
+    pub countermodel: Option<CounterModel>,
+    /// Intuitionistic validity, `Unknown` on a timeout and `None` if the statement is
+    /// not propositional.
+    pub intuitionistic: Option<Verdict>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct LatexTreeRequest {
+    pub tree: CreateTreeRequest,
+    #[serde(default)]
+    pub style: LatexStyle,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct LatexTree {
+    pub proof: String,
+    pub document: String,
+}
+
+/// A graph in the Graphviz DOT language.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DotGraph {
+    pub dot: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CreateSessionRequest {
+    pub exercise_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SessionApplyParams {
+    /// The open goal the rule is applied to.
+    pub goal: Uuid,
+    pub rule: Rules,
+    pub mapping: Vec<FormulaMapping>,
+    pub substitution: Vec<ElementMapping>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ProofSession {
+    pub id: Uuid,
+    pub exercise_id: Uuid,
+    pub statement: Statement,
+    /// The rules applied so far, the open goals are not part of the tree.
+    pub tree: CreateTreeRequest,
+    pub goals: Vec<Goal>,
+    pub can_undo: bool,
+    pub can_redo: bool,
+    pub completed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct HintGraphCheckParams {
+    /// Delete the stored derivations that are no valid rule applications.
+    #[serde(default)]
+    pub delete_invalid: bool,
+    /// Delete the statements that no exercise reaches.
+    #[serde(default)]
+    pub remove_unreachable: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct InvalidDerivation {
+    pub statement: Uuid,
+    pub rule: Rules,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, Default)]
+pub struct HintGraphReport {
+    /// The number of checked derivations.
+    pub checked: usize,
+    pub invalid: Vec<InvalidDerivation>,
+    /// The statements along every cycle of the graph.
+    pub cycles: Vec<Vec<Uuid>>,
+    pub unreachable: Vec<Uuid>,
 }
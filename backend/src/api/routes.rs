@@ -1,26 +1,63 @@
 use axum::extract::{Path, State};
 use axum::Json;
-use log::info;
-use sea_orm::{ActiveModelTrait, IntoActiveModel, ModelTrait, QueryFilter, TransactionTrait};
+use log::{error, info};
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, IntoActiveModel, ModelTrait, QueryFilter, TransactionTrait,
+};
 use std::collections::{BTreeMap, BTreeSet};
 use uuid::Uuid;
 
 use crate::db::*;
 use crate::error::{BackendError, BackendResult};
 use crate::lib::derivation::formula::Formula;
+use crate::lib::derivation::sat::{SolverLimits, Validity, Verdict};
+use crate::lib::derivation::search::SearchLimits;
 use crate::lib::derivation::statement::Statement;
-use crate::lib::derivation::tree::{check_tree, infer_mapping_stmt};
+use crate::lib::derivation::tree::{check_tree, merge_fragments, validate_tree, TreeValidation};
 use crate::lib::rule::{DerivationRule, RuleIdentifier, Rules};
 use crate::AppState;
 use sea_orm::EntityTrait;
 
 use super::models::{
-    ApplyRuleParams, CreateExerciseRequest, CreateTreeRequest, ElementMapping, Exercise, Feedback,
-    FormulaMapping, Node, ParseParams, SideCondition, Tipp,
+    ApplicableRulesParams, AppliedRule, ApplyRuleParams, CheckResult, CreateExerciseRequest,
+    CreateSessionRequest, CreateTreeRequest, DotGraph, ElementMapping, Exercise, Feedback,
+    FormulaMapping, ForwardRuleParams, HintGraphCheckParams, HintGraphReport, HintLevel,
+    HintParams, LatexTree, LatexTreeRequest, MergeTreeRequest, ParseParams, ParsedFormula,
+    ProofSearchResult, ProofSession, ProveParams, SessionApplyParams, Tipp,
 };
+use crate::lib::db::graph::HintGraph;
+use crate::lib::db::maintenance;
+use crate::lib::dot::{hint_graph_to_dot, tree_to_dot};
+use crate::lib::latex::{latex_document, tree_to_latex, Latex, ToLatex};
+use crate::lib::parser::ParseError;
+use crate::lib::session::{Goal, ProofState};
 use crate::lib::{db, LogicParser};
 use sea_orm::ColumnTrait;
 
+// The statement of the exercise as entered, `None` if it is missing.
+async fn exercise_statement(
+    db: &impl ConnectionTrait,
+    exercise: &exercise::Model,
+) -> BackendResult<Option<Statement>> {
+    if let Some(original) = &exercise.original {
+        let statement = serde_json::from_str(original).map_err(|e| {
+            BackendError::Unknown(format!(
+                "failed to deserialize exercise {}: {e}",
+                exercise.id
+            ))
+        })?;
+        return Ok(Some(statement));
+    }
+    // Exercises stored before only have their normal form.
+    match statement::Entity::find_by_id(exercise.statement_id)
+        .one(db)
+        .await?
+    {
+        Some(statement) => Ok(Some(db::parse_statement(&statement)?)),
+        None => Ok(None),
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/api/exercise",
@@ -34,38 +71,23 @@ pub async fn get_exercises(state: State<AppState>) -> BackendResult<Json<Vec<Exe
 
     let mut result = Vec::new();
     for e in exercises.iter() {
-        let exercise = statement::Entity::find_by_id(e.statement_id)
-            .one(&state.db)
-            .await?;
-        if exercise.is_none() {
+        let Some(statement) = exercise_statement(&state.db, e).await? else {
             continue;
-        }
-        let exercise = exercise.unwrap();
+        };
         let hint_available = node::Entity::find()
-            .filter(node::Column::ParentId.eq(exercise.id))
+            .filter(node::Column::ParentId.eq(e.statement_id))
             .one(&state.db)
             .await?
             .is_some();
-        let formula = serde_json::from_str::<Formula>(&exercise.rhs)
-            .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
-
-        let lhs = serde_json::from_str::<Vec<Formula>>(&exercise.lhs)
-            .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
-
-        let sidecondition = serde_json::from_str::<Vec<_>>(&exercise.sidecondition)
-            .map_err(|e| BackendError::BadRequest(format!("failed to serialize asdf: {e}")))?;
 
         result.push(Exercise {
             id: e.id,
             likes: e.likes,
             dislikes: e.dislikes,
             difficulty: e.difficulty,
-            exercise: Statement {
-                lhs,
-                formula,
-                sidecondition,
-            },
+            exercise: statement,
             hint: hint_available,
+            classical: e.classical,
         });
     }
 
@@ -92,33 +114,67 @@ pub async fn get_exercise(
         });
     }
 
-    let statement = statement::Entity::find_by_id(exercises.unwrap().statement_id)
-        .one(&state.db)
-        .await?;
-
-    if statement.is_none() {
-        return Err(BackendError::NotFound {
+    let exercise = exercise_statement(&state.db, &exercises.unwrap())
+        .await?
+        .ok_or(BackendError::NotFound {
             entity: "Statement".to_string(),
-        });
-    }
-
-    let statement = statement.unwrap();
-    let formula = serde_json::from_str::<Formula>(&statement.rhs)
-        .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
-
-    let lhs = serde_json::from_str::<Vec<Formula>>(&statement.lhs)
-        .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
+        })?;
 
-    let sidecondition = serde_json::from_str::<Vec<SideCondition>>(&statement.sidecondition)
-        .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
+    Ok(Json(exercise))
+}
 
-    let exercise = Statement {
-        formula,
-        lhs,
-        sidecondition,
-    };
+#[utoipa::path(
+    get,
+    path = "/api/exercise/{id}/latex",
+    responses(
+        (status = StatusCode::OK, body = Latex<Statement>),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn get_exercise_latex(
+    state: State<AppState>,
+    id: Path<Uuid>,
+) -> BackendResult<Json<Latex<Statement>>> {
+    let Json(statement) = get_exercise(state, id).await?;
+    Ok(Json(statement.to_latex()))
+}
 
-    Ok(Json(exercise))
+#[utoipa::path(
+    get,
+    path = "/api/exercise/{id}/solution",
+    responses(
+        (status = StatusCode::OK, body = ProofSearchResult),
+        (status = StatusCode::NOT_FOUND, description = "Exercise not found"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn get_exercise_solution(
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BackendResult<Json<ProofSearchResult>> {
+    let exercise = exercise::Entity::find_by_id(id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| BackendError::IdNotFound {
+            entity: "Exercise".to_string(),
+            id: id.to_string(),
+        })?;
+    let graph = HintGraph::load(&state.db, exercise.statement_id).await?;
+    let mut tree = graph.shortest_derivation();
+    // The derivation is stored in normal form, it is shown in the names of the exercise.
+    if let (Some(tree), Some(statement)) = (
+        tree.as_mut(),
+        exercise_statement(&state.db, &exercise).await?,
+    ) {
+        let mut renaming = statement.normalize().1.inverse();
+        for node in tree.nodes.iter_mut() {
+            node.statement = renaming.statement(&node.statement);
+        }
+    }
+    Ok(Json(ProofSearchResult {
+        found: tree.is_some(),
+        tree,
+    }))
 }
 
 #[utoipa::path(
@@ -133,29 +189,51 @@ pub async fn create_exercise(
     state: State<AppState>,
     query: Json<CreateExerciseRequest>,
 ) -> BackendResult<Json<bool>> {
-    let res = query.statement.check();
-
-    if !res {
-        return Err(BackendError::BadRequest(
-            "The formula is not a tautology".to_string(),
-        ));
+    query.statement.well_formed()?;
+    let limits = SolverLimits::bounded(query.timeout_ms);
+    match query.statement.validate_blocking(limits).await? {
+        Validity::Valid => {}
+        Validity::Invalid(countermodel) => {
+            let reason = match countermodel {
+                Some(model) => format!("The formula is not a tautology. Countermodel: {model}"),
+                None => "The formula is not a tautology".to_string(),
+            };
+            return Err(BackendError::BadRequest(reason));
+        }
+        Validity::Unknown(reason) => {
+            return Err(BackendError::BadRequest(format!(
+                "Could not decide whether the formula is a tautology: {reason}"
+            )));
+        }
+    }
+    if !query.classical {
+        match query
+            .statement
+            .check_intuitionistic_blocking(limits)
+            .await?
+        {
+            Some(Verdict::Invalid) => {
+                return Err(BackendError::BadRequest(
+                    "The formula is only classically valid, create it as a classical exercise"
+                        .to_string(),
+                ));
+            }
+            Some(Verdict::Unknown) => {
+                return Err(BackendError::BadRequest(
+                    "Could not decide whether the formula is intuitionistically valid in time"
+                        .to_string(),
+                ));
+            }
+            Some(Verdict::Valid) | None => {}
+        }
     }
 
-    let rhs = serde_json::to_string(&query.statement.formula)
-        .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
-    let lhs = serde_json::to_string(&query.statement.lhs)
-        .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
-
-    println!("{:?}", query.statement.sidecondition);
-    let sidecondition = serde_json::to_string(&query.statement.sidecondition)
+    // Exercises are stored in normal form, so renamed duplicates are detected.
+    let (normalized, _) = query.statement.normalize();
+    let original = serde_json::to_string(&query.statement)
         .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
 
-    let exists = statement::Entity::find()
-        .filter(statement::Column::Lhs.eq(&lhs))
-        .filter(statement::Column::Rhs.eq(&rhs))
-        .filter(statement::Column::Sidecondition.eq(&sidecondition))
-        .one(&state.db)
-        .await?;
+    let exists = db::find_statement(&state.db, &normalized).await?;
 
     let exercise = if let Some(stmt) = exists {
         let ex = exercise::Entity::find()
@@ -172,28 +250,40 @@ pub async fn create_exercise(
                 dislikes: sea_orm::ActiveValue::Set(0),
                 likes: sea_orm::ActiveValue::Set(0),
                 statement_id: sea_orm::ActiveValue::Set(stmt.id),
+                classical: sea_orm::ActiveValue::Set(query.classical),
+                original: sea_orm::ActiveValue::Set(Some(original)),
                 ..Default::default()
             },
         }
     } else {
-        let node = statement::ActiveModel {
-            lhs: sea_orm::ActiveValue::Set(lhs),
-            rhs: sea_orm::ActiveValue::Set(rhs),
-            sidecondition: sea_orm::ActiveValue::Set(sidecondition),
-            ..Default::default()
-        };
-
-        let statement = node.save(&state.db).await?;
+        let statement = db::insert_statement(&state.db, &normalized).await?;
 
         exercise::ActiveModel {
             dislikes: sea_orm::ActiveValue::Set(0),
             likes: sea_orm::ActiveValue::Set(0),
-            statement_id: statement.id,
+            statement_id: sea_orm::ActiveValue::Set(statement.id),
+            classical: sea_orm::ActiveValue::Set(query.classical),
+            original: sea_orm::ActiveValue::Set(Some(original)),
             ..Default::default()
         }
     };
     let _ = exercise.save(&state.db).await?;
 
+    // Store a generated solution so hints are available right away.
+    let limits = SearchLimits::default().with_classical(query.classical);
+    match normalized.search_proof_blocking(limits).await? {
+        // Only checked derivations may enter the hint graph.
+        Some(tree) => match check_tree(tree.root_id, &tree.nodes) {
+            Ok(()) => {
+                let trx = state.db.begin().await?;
+                let _ = db::add_tree(&trx, tree.root_id, &tree.nodes).await?;
+                trx.commit().await?;
+            }
+            Err(err) => error!("generated proof of {} is invalid: {}", normalized, err),
+        },
+        None => info!("no proof found for {}", normalized),
+    }
+
     Ok(Json(true))
 }
 
@@ -201,17 +291,15 @@ pub async fn create_exercise(
     post,
     path = "/api/parse",
     responses(
-        (status = StatusCode::OK, body = Formula),
-        (status = StatusCode::NOT_FOUND, description = "Building not found"),
+        (status = StatusCode::OK, body = ParsedFormula),
+        (status = StatusCode::BAD_REQUEST, body = ParseError),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
     )
 )]
-pub async fn parse(query: Json<ParseParams>) -> BackendResult<Json<Formula>> {
-    let f = LogicParser::parse_input(&query.formula);
-    match f {
-        Ok(formula) => Ok(Json(formula)),
-        Err(err) => Err(BackendError::BadRequest(err)),
-    }
+pub async fn parse(query: Json<ParseParams>) -> BackendResult<Json<ParsedFormula>> {
+    let formula = LogicParser::parse_input(&query.formula).map_err(BackendError::ParseError)?;
+    let signature = formula.signature()?;
+    Ok(Json(ParsedFormula { formula, signature }))
 }
 
 #[utoipa::path(
@@ -228,59 +316,218 @@ pub async fn all_rules() -> BackendResult<Json<Vec<DerivationRule>>> {
     Ok(Json(rules.into_iter().collect::<Vec<_>>()))
 }
 
+// The mappings chosen by the user keyed by the identifiers of the rule.
+fn rule_mappings(
+    mapping: &[FormulaMapping],
+    substitution: &[ElementMapping],
+) -> (
+    BTreeMap<RuleIdentifier, Formula>,
+    BTreeMap<RuleIdentifier, String>,
+) {
+    let formula_mapping = mapping
+        .iter()
+        .map(|map| (RuleIdentifier::Formula(map.from), map.to.clone()))
+        .collect();
+    let element_mapping = substitution
+        .iter()
+        .map(|map| (RuleIdentifier::Element(map.from.clone()), map.to.clone()))
+        .collect();
+    (formula_mapping, element_mapping)
+}
+
+// The inferred mappings in the form the client sends them.
+fn client_mappings(
+    mapping: &BTreeMap<RuleIdentifier, Formula>,
+    substitution: &BTreeMap<RuleIdentifier, String>,
+) -> (Vec<FormulaMapping>, Vec<ElementMapping>) {
+    let formula_mapping = mapping
+        .iter()
+        .filter_map(|(from, to)| match from {
+            RuleIdentifier::Formula(from) => Some(FormulaMapping {
+                from: *from,
+                to: to.clone(),
+            }),
+            _ => None,
+        })
+        .collect();
+    let element_mapping = substitution
+        .iter()
+        .filter_map(|(from, to)| match from {
+            RuleIdentifier::Element(from) => Some(ElementMapping {
+                from: from.clone(),
+                to: to.clone(),
+            }),
+            _ => None,
+        })
+        .collect();
+    (formula_mapping, element_mapping)
+}
+
+// Applies the rule as far as the mappings are known. The premisses are only computed,
+// and the side conditions only checked, once no identifier is unbound.
+fn apply_partially(
+    statement: &Statement,
+    rule: DerivationRule,
+    mut formula_mapping: BTreeMap<RuleIdentifier, Formula>,
+    mut element_mapping: BTreeMap<RuleIdentifier, String>,
+) -> BackendResult<AppliedRule> {
+    let unbound =
+        statement.infer_rule_mapping(&rule, &mut formula_mapping, &mut element_mapping)?;
+    let (mapping, substitution) = client_mappings(&formula_mapping, &element_mapping);
+    let name = rule.name.clone();
+    let premisses = if unbound.is_empty() {
+        statement.apply_rule(rule, &mut formula_mapping, &mut element_mapping)?
+    } else {
+        Vec::new()
+    };
+    Ok(AppliedRule {
+        rule: name,
+        premisses,
+        mapping,
+        substitution,
+        unbound: unbound.into_iter().collect(),
+    })
+}
+
 #[utoipa::path(
     post,
     path = "/api/apply",
     responses(
-        (status = StatusCode::OK, body = Vec<Statement>),
+        (status = StatusCode::OK, body = AppliedRule),
         (status = StatusCode::NOT_FOUND, description = "Building not found"),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
     )
 )]
-pub async fn apply_rule(query: Json<ApplyRuleParams>) -> BackendResult<Json<Vec<Statement>>> {
-    let rule = query.rule.get_rule();
-
-    let mut formula_mapping = query
-        .mapping
-        .clone()
-        .into_iter()
-        .map(|map| (RuleIdentifier::Formula(map.from), map.to))
-        .collect::<BTreeMap<RuleIdentifier, Formula>>();
+pub async fn apply_rule(query: Json<ApplyRuleParams>) -> BackendResult<Json<AppliedRule>> {
+    query.well_formed()?;
+    let (formula_mapping, element_mapping) = rule_mappings(&query.mapping, &query.substitution);
+    Ok(Json(apply_partially(
+        &query.statement,
+        query.rule.get_rule(),
+        formula_mapping,
+        element_mapping,
+    )?))
+}
 
-    let mut element_mapping = query
-        .substitution
-        .clone()
+#[utoipa::path(
+    post,
+    path = "/api/rules/applicable",
+    responses(
+        (status = StatusCode::OK, body = Vec<AppliedRule>),
+        (status = StatusCode::BAD_REQUEST, description = "Invalid statement"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn applicable_rules(
+    query: Json<ApplicableRulesParams>,
+) -> BackendResult<Json<Vec<AppliedRule>>> {
+    query.statement.well_formed()?;
+    let applicable = Rules::all_rules()
         .into_iter()
-        .map(|map| (RuleIdentifier::Element(map.from), map.to))
-        .collect::<BTreeMap<RuleIdentifier, String>>();
+        .filter(|rule| query.classical || !rule.name.is_classical())
+        .filter_map(|rule| {
+            apply_partially(&query.statement, rule, BTreeMap::new(), BTreeMap::new()).ok()
+        })
+        .collect();
+    Ok(Json(applicable))
+}
 
-    let new_premisses =
-        query
-            .statement
-            .apply_rule(rule, &mut formula_mapping, &mut element_mapping)?;
-    Ok(Json(new_premisses))
+#[utoipa::path(
+    post,
+    path = "/api/apply/forward",
+    responses(
+        (status = StatusCode::OK, body = Statement),
+        (status = StatusCode::BAD_REQUEST, description = "The rule can not be applied"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn apply_forward(query: Json<ForwardRuleParams>) -> BackendResult<Json<Statement>> {
+    query.well_formed()?;
+    let rule = query.rule.get_rule();
+    let (mut formula_mapping, mut element_mapping) =
+        rule_mappings(&query.mapping, &query.substitution);
+    let conclusion = Statement::derive(
+        &rule,
+        &query.premisses,
+        query.context.as_deref(),
+        &mut formula_mapping,
+        &mut element_mapping,
+    )?;
+    Ok(Json(conclusion))
 }
 
 #[utoipa::path(
     post,
     path = "/api/check",
     responses(
-        (status = StatusCode::OK, body = bool),
+        (status = StatusCode::OK, body = CheckResult),
         (status = StatusCode::NOT_FOUND, description = "Building not found"),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
     )
 )]
-pub async fn check(query: Json<CreateExerciseRequest>) -> BackendResult<Json<bool>> {
-    let result = query.statement.check();
-    info!("{:?} is a tautology: {}", query.0, result);
+pub async fn check(query: Json<CreateExerciseRequest>) -> BackendResult<Json<CheckResult>> {
+    query.statement.well_formed()?;
+    let limits = SolverLimits::bounded(query.timeout_ms);
+    let intuitionistic = query
+        .statement
+        .check_intuitionistic_blocking(limits)
+        .await?;
+    let validity = query.statement.validate_blocking(limits).await?;
+    let verdict = validity.verdict();
+    let (countermodel, reason) = match validity {
+        Validity::Valid => (None, None),
+        Validity::Invalid(countermodel) => (countermodel, None),
+        Validity::Unknown(reason) => (None, Some(reason)),
+    };
+    let result = CheckResult {
+        valid: verdict == Verdict::Valid,
+        verdict,
+        reason,
+        countermodel,
+        intuitionistic,
+    };
+    info!("{:?} is a tautology: {}", query.0, result.valid);
     Ok(Json(result))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/tree/validate",
+    responses(
+        (status = StatusCode::OK, body = TreeValidation),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn validate(query: Json<CreateTreeRequest>) -> BackendResult<Json<TreeValidation>> {
+    query.well_formed()?;
+    Ok(Json(validate_tree(query.root_id, &query.nodes)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/tree/merge",
+    responses(
+        (status = StatusCode::OK, body = CreateTreeRequest),
+        (status = StatusCode::BAD_REQUEST, body = TreeValidation),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn merge_tree(query: Json<MergeTreeRequest>) -> BackendResult<Json<CreateTreeRequest>> {
+    query.well_formed()?;
+    let nodes = merge_fragments(query.root_id, &query.nodes, &query.goals)?;
+    check_tree(query.root_id, &nodes)?;
+    Ok(Json(CreateTreeRequest {
+        nodes,
+        root_id: query.root_id,
+    }))
+}
+
 #[utoipa::path(
     post,
     path = "/api/add_tree",
     responses(
         (status = StatusCode::OK, body = bool),
+        (status = StatusCode::BAD_REQUEST, body = TreeValidation),
         (status = StatusCode::NOT_FOUND, description = "Building not found"),
         (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
     )
@@ -289,6 +536,7 @@ pub async fn add_tree(
     state: State<AppState>,
     query: Json<CreateTreeRequest>,
 ) -> BackendResult<Json<bool>> {
+    query.well_formed()?;
     check_tree(query.root_id, &query.nodes)?;
     let trx = state.db.begin().await?;
     let _ = db::add_tree(&trx, query.root_id, &query.nodes).await?;
@@ -296,6 +544,87 @@ pub async fn add_tree(
     Ok(Json(true))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/statement/prove",
+    responses(
+        (status = StatusCode::OK, body = ProofSearchResult),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn prove(query: Json<ProveParams>) -> BackendResult<Json<ProofSearchResult>> {
+    query.statement.well_formed()?;
+    let limits =
+        SearchLimits::bounded(query.max_depth, query.timeout_ms).with_classical(query.classical);
+    let tree = query.statement.search_proof_blocking(limits).await?;
+    info!(
+        "proof search for {}: found {}",
+        query.statement,
+        tree.is_some()
+    );
+    Ok(Json(ProofSearchResult {
+        found: tree.is_some(),
+        tree,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/tree/latex",
+    responses(
+        (status = StatusCode::OK, body = LatexTree),
+        (status = StatusCode::BAD_REQUEST, description = "Malformed tree"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn tree_latex(query: Json<LatexTreeRequest>) -> BackendResult<Json<LatexTree>> {
+    query.tree.well_formed()?;
+    let proof = tree_to_latex(query.tree.root_id, &query.tree.nodes, query.style)?;
+    let document = latex_document(&proof, query.style);
+    Ok(Json(LatexTree { proof, document }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/tree/dot",
+    responses(
+        (status = StatusCode::OK, body = DotGraph),
+        (status = StatusCode::BAD_REQUEST, description = "Malformed tree"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn tree_dot(query: Json<CreateTreeRequest>) -> BackendResult<Json<DotGraph>> {
+    query.well_formed()?;
+    let dot = tree_to_dot(query.root_id, &query.nodes)?;
+    Ok(Json(DotGraph { dot }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/exercise/{id}/hints/dot",
+    responses(
+        (status = StatusCode::OK, body = DotGraph),
+        (status = StatusCode::NOT_FOUND, description = "Exercise not found"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn get_exercise_hints_dot(
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BackendResult<Json<DotGraph>> {
+    let exercise = exercise::Entity::find_by_id(id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| BackendError::IdNotFound {
+            entity: "Exercise".to_string(),
+            id: id.to_string(),
+        })?;
+    let graph = HintGraph::load(&state.db, exercise.statement_id).await?;
+    Ok(Json(DotGraph {
+        dot: hint_graph_to_dot(&graph),
+    }))
+}
+
 #[utoipa::path(
     post,
     path = "/api/exercise/{id}/feedback",
@@ -339,77 +668,381 @@ pub async fn post_feedback(
     Ok(Json(true))
 }
 
-#[utoipa::path(
-    post,
-    path = "/api/statement/hint",
-    responses(
-        (status = StatusCode::OK, body = Vec<Tipp>),
-        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
-    )
-)]
-pub async fn get_tipp(
-    state: State<AppState>,
-    query: Json<Statement>,
-) -> BackendResult<Json<Vec<Tipp>>> {
-    let lhs = serde_json::to_string(&query.lhs)
-        .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
-    let rhs = serde_json::to_string(&query.formula)
-        .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
-
-    let statement = statement::Entity::find()
-        .filter(statement::Column::Lhs.eq(&lhs))
-        .filter(statement::Column::Rhs.eq(&rhs))
-        .one(&state.db)
-        .await?;
+// The hints stored for the statement, empty if nobody derived it yet.
+/// A rule together with the edge set of one stored derivation.
+type Derivation = (Rules, Option<Uuid>);
 
+async fn stored_tipps(db: &impl ConnectionTrait, query: &Statement) -> BackendResult<Vec<Tipp>> {
+    // Statements equal up to renaming share their hints, which then have to be
+    // translated back into the names of the query.
+    let mut statement = db::find_statement(db, query).await?;
+    let mut renaming = None;
     if statement.is_none() {
-        return Err(BackendError::NotFound {
-            entity: "Statement".to_string(),
-        });
+        let (normalized, normalizer) = query.normalize();
+        statement = db::find_statement(db, &normalized).await?;
+        renaming = Some(normalizer.inverse());
     }
-    let statement = statement.unwrap();
+
+    let Some(statement) = statement else {
+        return Ok(Vec::new());
+    };
 
     let tipps = node::Entity::find()
         .filter(node::Column::ParentId.eq(statement.id))
-        .all(&state.db)
+        .all(db)
         .await?;
-    let mut sorted_tips: BTreeMap<Rules, Vec<(Statement, u32)>> = BTreeMap::new();
+    // Every stored derivation is a hint, several may use the same rule.
+    let mut sorted_tips: BTreeMap<Derivation, Vec<(Statement, u32)>> = BTreeMap::new();
+    let mut usage: BTreeMap<Derivation, (i32, i32)> = BTreeMap::new();
 
     for node in tipps {
-        let mut premisses = sorted_tips
-            .get(&node.rule.clone().into())
-            .unwrap_or(&Vec::new())
-            .clone();
+        let key = (node.rule.clone().into(), node.derivation);
+        let (uses, size) = usage.entry(key.clone()).or_insert((node.uses, node.size));
+        *uses = (*uses).max(node.uses);
+        *size = (*size).min(node.size);
+
+        let mut premisses = sorted_tips.get(&key).unwrap_or(&Vec::new()).clone();
 
         if let Some(premisse) = node.child_id {
-            let premiss = statement::Entity::find_by_id(premisse)
-                .one(&state.db)
-                .await?;
+            let premiss = match db::parse_premiss(&node)? {
+                Some(premiss) => Some(premiss),
+                None => match statement::Entity::find_by_id(premisse).one(db).await? {
+                    Some(premiss) => Some(db::parse_statement(&premiss)?),
+                    None => None,
+                },
+            };
 
             if let Some(premisse) = premiss {
-                premisses.push((
-                    Statement {
-                        lhs: serde_json::from_str(&premisse.lhs).unwrap(),
-                        formula: serde_json::from_str(&premisse.rhs).unwrap(),
-                        sidecondition: serde_json::from_str(&premisse.sidecondition).unwrap(),
-                    },
-                    node.order as u32,
-                ));
+                let premisse = match renaming.as_mut() {
+                    Some(renaming) => renaming.statement(&premisse),
+                    None => premisse,
+                };
+                premisses.push((premisse, node.order as u32));
             }
         }
-        sorted_tips.insert(node.rule.into(), premisses);
+        sorted_tips.insert(key, premisses);
     }
 
-    let result = sorted_tips
+    let mut result = sorted_tips
         .iter()
-        .map(|(rule, premisses)| {
+        .map(|(key, premisses)| {
             let mut sorted_premisses = premisses.clone();
             sorted_premisses.sort_by(|(_, a), (_, b)| a.cmp(b));
+            let (uses, size) = usage.get(key).cloned().unwrap_or((0, 1));
+            let (rule, _) = key;
             Tipp {
                 rule: rule.clone(),
                 premisses: sorted_premisses.iter().map(|(s, _)| s.clone()).collect(),
+                tree: None,
+                uses: uses.max(0) as u32,
+                size: size.max(1) as u32,
             }
         })
         .collect::<Vec<_>>();
-    Ok(Json(result))
+    // Derivations that are used often and short come first.
+    let score = |tipp: &Tipp| (tipp.uses + 1) as f64 / tipp.size as f64;
+    result.sort_by(|a, b| score(b).total_cmp(&score(a)));
+    Ok(result)
+}
+
+// The first step of a derivation found by the proof search.
+fn tipp_from_tree(tree: CreateTreeRequest) -> BackendResult<Tipp> {
+    let root = tree
+        .nodes
+        .iter()
+        .find(|node| node.name == tree.root_id)
+        .ok_or_else(|| BackendError::Unknown("The derivation has no root".to_string()))?;
+    let premisses = root
+        .premisses
+        .iter()
+        .filter_map(|id| tree.nodes.iter().find(|node| node.name == *id))
+        .map(|node| node.statement.clone())
+        .collect();
+    Ok(Tipp {
+        rule: root.rule.clone(),
+        premisses,
+        uses: 0,
+        size: tree.nodes.len() as u32,
+        tree: Some(tree),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/statement/hint",
+    responses(
+        (status = StatusCode::OK, body = Vec<Tipp>),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn get_tipp(
+    state: State<AppState>,
+    query: Json<HintParams>,
+) -> BackendResult<Json<Vec<Tipp>>> {
+    query.statement.well_formed()?;
+    let limits = SearchLimits::default().with_classical(query.classical);
+
+    let mut tipps = stored_tipps(&state.db, &query.statement).await?;
+    // Classical steps taken in other exercises are no help in an intuitionistic one.
+    tipps.retain(|tipp| query.classical || !tipp.rule.is_classical());
+    if tipps.is_empty() {
+        // Nobody took this step yet, the proof search suggests one.
+        if let Some(tree) = query.statement.search_proof_blocking(limits).await? {
+            tipps.push(tipp_from_tree(tree)?);
+        }
+    } else if query.level == HintLevel::Tree {
+        let steps = tipps
+            .iter()
+            .map(|tipp| (tipp.rule.clone(), tipp.premisses.clone()))
+            .collect();
+        let trees = query
+            .statement
+            .complete_steps_blocking(steps, limits)
+            .await?;
+        for (tipp, tree) in tipps.iter_mut().zip(trees) {
+            tipp.tree = tree;
+        }
+    }
+
+    for tipp in tipps.iter_mut() {
+        match query.level {
+            HintLevel::Rule => {
+                tipp.premisses.clear();
+                tipp.tree = None;
+            }
+            HintLevel::Premisses => tipp.tree = None,
+            HintLevel::Tree => {}
+        }
+    }
+    Ok(Json(tipps))
+}
+
+fn session_response(
+    session: proof_session::Model,
+    state: &ProofState,
+) -> BackendResult<ProofSession> {
+    Ok(ProofSession {
+        id: session.id,
+        exercise_id: session.exercise_id,
+        statement: state.statement()?.clone(),
+        tree: state.tree(),
+        goals: state.open_goals(),
+        can_undo: !session.completed && !state.steps.is_empty(),
+        can_redo: !session.completed && !state.undone.is_empty(),
+        completed: session.completed,
+    })
+}
+
+fn check_open(session: &proof_session::Model) -> BackendResult<()> {
+    if session.completed {
+        return Err(BackendError::BadRequest(
+            "The proof is already complete".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/session",
+    responses(
+        (status = StatusCode::OK, body = ProofSession),
+        (status = StatusCode::NOT_FOUND, description = "Exercise not found"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn create_session(
+    state: State<AppState>,
+    query: Json<CreateSessionRequest>,
+) -> BackendResult<Json<ProofSession>> {
+    let exercise = exercise::Entity::find_by_id(query.exercise_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| BackendError::IdNotFound {
+            entity: "Exercise".to_string(),
+            id: query.exercise_id.to_string(),
+        })?;
+    let Json(statement) = get_exercise(state.clone(), Path(exercise.id)).await?;
+
+    let proof = ProofState::new(statement, exercise.classical);
+    let serialized = serde_json::to_string(&proof)
+        .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
+    let session = proof_session::ActiveModel {
+        exercise_id: sea_orm::ActiveValue::Set(exercise.id),
+        state: sea_orm::ActiveValue::Set(serialized),
+        completed: sea_orm::ActiveValue::Set(false),
+        ..Default::default()
+    }
+    .insert(&state.db)
+    .await?;
+    Ok(Json(session_response(session, &proof)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/session/{id}",
+    responses(
+        (status = StatusCode::OK, body = ProofSession),
+        (status = StatusCode::NOT_FOUND, description = "Session not found"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn get_session(
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BackendResult<Json<ProofSession>> {
+    let (session, proof) = db::find_session(&state.db, id).await?;
+    Ok(Json(session_response(session, &proof)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/session/{id}/goals",
+    responses(
+        (status = StatusCode::OK, body = Vec<Goal>),
+        (status = StatusCode::NOT_FOUND, description = "Session not found"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn session_goals(
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BackendResult<Json<Vec<Goal>>> {
+    let (_, proof) = db::find_session(&state.db, id).await?;
+    Ok(Json(proof.open_goals()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/session/{id}/apply",
+    responses(
+        (status = StatusCode::OK, body = ProofSession),
+        (status = StatusCode::BAD_REQUEST, description = "The rule can not be applied"),
+        (status = StatusCode::NOT_FOUND, description = "Session not found"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn session_apply(
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+    query: Json<SessionApplyParams>,
+) -> BackendResult<Json<ProofSession>> {
+    query.well_formed()?;
+    let trx = state.db.begin().await?;
+    let (session, mut proof) = db::find_session(&trx, id).await?;
+    check_open(&session)?;
+    let (mut formula_mapping, mut element_mapping) =
+        rule_mappings(&query.mapping, &query.substitution);
+    proof.apply(
+        query.goal,
+        query.rule.clone(),
+        &mut formula_mapping,
+        &mut element_mapping,
+    )?;
+    let session = db::save_session(&trx, session, &proof).await?;
+    trx.commit().await?;
+    Ok(Json(session_response(session, &proof)?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/session/{id}/undo",
+    responses(
+        (status = StatusCode::OK, body = ProofSession),
+        (status = StatusCode::BAD_REQUEST, description = "Nothing to undo"),
+        (status = StatusCode::NOT_FOUND, description = "Session not found"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn session_undo(
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BackendResult<Json<ProofSession>> {
+    let trx = state.db.begin().await?;
+    let (session, mut proof) = db::find_session(&trx, id).await?;
+    check_open(&session)?;
+    proof.undo()?;
+    let session = db::save_session(&trx, session, &proof).await?;
+    trx.commit().await?;
+    Ok(Json(session_response(session, &proof)?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/session/{id}/redo",
+    responses(
+        (status = StatusCode::OK, body = ProofSession),
+        (status = StatusCode::BAD_REQUEST, description = "Nothing to redo"),
+        (status = StatusCode::NOT_FOUND, description = "Session not found"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn session_redo(
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BackendResult<Json<ProofSession>> {
+    let trx = state.db.begin().await?;
+    let (session, mut proof) = db::find_session(&trx, id).await?;
+    check_open(&session)?;
+    proof.redo()?;
+    let session = db::save_session(&trx, session, &proof).await?;
+    trx.commit().await?;
+    Ok(Json(session_response(session, &proof)?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/session/{id}/complete",
+    responses(
+        (status = StatusCode::OK, body = ProofSession),
+        (status = StatusCode::BAD_REQUEST, body = TreeValidation),
+        (status = StatusCode::NOT_FOUND, description = "Session not found"),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn complete_session(
+    state: State<AppState>,
+    Path(id): Path<Uuid>,
+) -> BackendResult<Json<ProofSession>> {
+    let trx = state.db.begin().await?;
+    let (mut session, proof) = db::find_session(&trx, id).await?;
+    check_open(&session)?;
+    let open = proof.open_goals().len();
+    if open > 0 {
+        return Err(BackendError::BadRequest(format!(
+            "The proof still has {open} open goals"
+        )));
+    }
+    // Finished proofs are added to the hints like uploaded trees.
+    let tree = proof.tree();
+    check_tree(tree.root_id, &tree.nodes)?;
+    let _ = db::add_tree(&trx, tree.root_id, &tree.nodes).await?;
+
+    session.completed = true;
+    let session = db::save_session(&trx, session, &proof).await?;
+    trx.commit().await?;
+    Ok(Json(session_response(session, &proof)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/hints/check",
+    responses(
+        (status = StatusCode::OK, body = HintGraphReport),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, description = "Internal server error")
+    )
+)]
+pub async fn check_hints(state: State<AppState>) -> BackendResult<Json<HintGraphReport>> {
+    // Only reports, deleting is left to the `check-hints` command of the binary.
+    let trx = state.db.begin().await?;
+    let report = maintenance::check_hint_graph(&trx, &HintGraphCheckParams::default()).await?;
+    trx.rollback().await?;
+    info!(
+        "checked {} derivations: {} invalid, {} cycles, {} unreachable statements",
+        report.checked,
+        report.invalid.len(),
+        report.cycles.len(),
+        report.unreachable.len()
+    );
+    Ok(Json(report))
 }
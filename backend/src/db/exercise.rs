@@ -14,10 +14,15 @@ pub struct Model {
     #[sea_orm(column_type = "Double")]
     pub difficulty: f64,
     pub num_responses: i32,
+    pub classical: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub original: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::proof_session::Entity")]
+    ProofSession,
     #[sea_orm(
         belongs_to = "super::statement::Entity",
         from = "Column::StatementId",
@@ -28,6 +33,12 @@ pub enum Relation {
     Statement,
 }
 
+impl Related<super::proof_session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProofSession.def()
+    }
+}
+
 impl Related<super::statement::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Statement.def()
@@ -1,8 +1,7 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
-pub mod prelude;
-
 pub mod exercise;
 pub mod node;
+pub mod proof_session;
 pub mod sea_orm_active_enums;
 pub mod statement;
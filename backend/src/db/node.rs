@@ -12,6 +12,11 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
     pub order: i32,
+    pub uses: i32,
+    pub size: i32,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub premiss: Option<String>,
+    pub derivation: Option<Uuid>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,5 +0,0 @@
-//! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
-
-pub use super::exercise::Entity as Exercise;
-pub use super::node::Entity as Node;
-pub use super::statement::Entity as Statement;
@@ -41,4 +41,14 @@ pub enum Rules {
     AlphaExists,
     #[sea_orm(string_value = "AlphaForall")]
     AlphaForall,
+    #[sea_orm(string_value = "EqIntro")]
+    EqIntro,
+    #[sea_orm(string_value = "EqElim")]
+    EqElim,
+    #[sea_orm(string_value = "Raa")]
+    Raa,
+    #[sea_orm(string_value = "Lem")]
+    Lem,
+    #[sea_orm(string_value = "DoubleNegElim")]
+    DoubleNegElim,
 }
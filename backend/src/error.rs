@@ -1,10 +1,13 @@
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
+    Json,
 };
 use log::error;
 use thiserror::Error;
 
+use crate::lib::{derivation::tree::TreeValidation, parser::ParseError};
+
 #[derive(Error, Debug)]
 pub enum BackendError {
     #[error("DB error: {0}")]
@@ -21,6 +24,12 @@ pub enum BackendError {
 
     #[error("Unknown error: {0}")]
     Unknown(String),
+
+    #[error("Invalid derivation tree")]
+    InvalidTree(Box<TreeValidation>),
+
+    #[error("Could not parse formula: {0}")]
+    ParseError(Box<ParseError>),
 }
 
 impl BackendError {
@@ -31,6 +40,8 @@ impl BackendError {
             BackendError::IdNotFound { .. } => StatusCode::NOT_FOUND,
             BackendError::BadRequest(_) => StatusCode::BAD_REQUEST,
             BackendError::Unknown(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            BackendError::InvalidTree(_) => StatusCode::BAD_REQUEST,
+            BackendError::ParseError(_) => StatusCode::BAD_REQUEST,
         }
     }
 
@@ -41,6 +52,8 @@ impl BackendError {
             BackendError::IdNotFound { .. } => self.to_string(),
             BackendError::BadRequest(_) => self.to_string(),
             BackendError::Unknown(_) => "Internal server error".to_string(),
+            BackendError::InvalidTree(_) => self.to_string(),
+            BackendError::ParseError(_) => self.to_string(),
         }
     }
 }
@@ -48,7 +61,13 @@ impl BackendError {
 impl IntoResponse for BackendError {
     fn into_response(self) -> Response {
         error!("Error: {}", &self);
-        (self.status_code(), self.status_text()).into_response()
+        match self {
+            BackendError::InvalidTree(report) => {
+                (StatusCode::BAD_REQUEST, Json(report)).into_response()
+            }
+            BackendError::ParseError(err) => (StatusCode::BAD_REQUEST, Json(err)).into_response(),
+            _ => (self.status_code(), self.status_text()).into_response(),
+        }
     }
 }
 
@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use uuid::Uuid;
+
+use crate::api::models::{CreateTreeRequest, Node};
+use crate::db::{node, statement};
+use crate::error::BackendResult;
+use crate::lib::derivation::statement::Statement;
+use crate::lib::rule::Rules;
+
+use super::{parse_premiss, parse_statement};
+
+/// A stored derivation step: the rule and the ids of its premisses in order, together
+/// with the premisses in the names of the conclusion.
+#[derive(Debug, Clone)]
+pub struct Derivation {
+    pub rule: Rules,
+    pub premisses: Vec<Uuid>,
+    pub statements: Vec<Statement>,
+}
+
+/// The part of the hint graph reachable from a statement. The graph may contain cycles.
+#[derive(Debug, Clone)]
+pub struct HintGraph {
+    pub root: Uuid,
+    pub statements: BTreeMap<Uuid, Statement>,
+    pub derivations: BTreeMap<Uuid, Vec<Derivation>>,
+}
+
+impl HintGraph {
+    pub async fn load(trx: &impl ConnectionTrait, root: Uuid) -> BackendResult<HintGraph> {
+        let mut graph = HintGraph {
+            root,
+            statements: BTreeMap::new(),
+            derivations: BTreeMap::new(),
+        };
+        let mut pending = vec![root];
+        let mut visited = BTreeSet::new();
+        let mut stored = BTreeMap::new();
+        while let Some(id) = pending.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            let Some(row) = statement::Entity::find_by_id(id).one(trx).await? else {
+                continue;
+            };
+            graph.statements.insert(id, parse_statement(&row)?);
+
+            let mut edges = node::Entity::find()
+                .filter(node::Column::ParentId.eq(id))
+                .all(trx)
+                .await?;
+            edges.sort_by_key(|edge| edge.order);
+            let mut by_rule =
+                BTreeMap::<(Rules, Option<Uuid>), Vec<(Uuid, Option<Statement>)>>::new();
+            for edge in edges {
+                let premiss = parse_premiss(&edge)?;
+                let premisses = by_rule
+                    .entry((edge.rule.into(), edge.derivation))
+                    .or_default();
+                if let Some(child) = edge.child_id {
+                    premisses.push((child, premiss));
+                    pending.push(child);
+                }
+            }
+            stored.insert(id, by_rule);
+        }
+
+        // Edges stored without their premiss use the names of the conclusion for the child.
+        for (id, by_rule) in stored {
+            let derivations = by_rule
+                .into_iter()
+                .filter_map(|((rule, _), premisses)| {
+                    let statements = premisses
+                        .iter()
+                        .map(|(child, premiss)| {
+                            premiss
+                                .clone()
+                                .or_else(|| graph.statements.get(child).cloned())
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    Some(Derivation {
+                        rule,
+                        premisses: premisses.into_iter().map(|(child, _)| child).collect(),
+                        statements,
+                    })
+                })
+                .collect();
+            graph.derivations.insert(id, derivations);
+        }
+        Ok(graph)
+    }
+
+    /// The number of rules of the shortest closed derivation of every statement that has
+    /// one. Derivations running in a cycle never close.
+    pub fn derivation_sizes(&self) -> BTreeMap<Uuid, usize> {
+        let mut sizes = BTreeMap::<Uuid, usize>::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (id, derivations) in self.derivations.iter() {
+                for derivation in derivations {
+                    let premisses = derivation
+                        .premisses
+                        .iter()
+                        .map(|premiss| sizes.get(premiss).copied())
+                        .sum::<Option<usize>>();
+                    let Some(premisses) = premisses else {
+                        continue;
+                    };
+                    if sizes.get(id).is_none_or(|size| premisses + 1 < *size) {
+                        sizes.insert(*id, premisses + 1);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        sizes
+    }
+
+    /// The shortest closed derivation of the root, `None` if the graph contains none.
+    pub fn shortest_derivation(&self) -> Option<CreateTreeRequest> {
+        let sizes = self.derivation_sizes();
+        let mut nodes = Vec::new();
+        let root = self.statements.get(&self.root)?.clone();
+        let root_id = self.build(self.root, root, &sizes, &mut BTreeSet::new(), &mut nodes)?;
+        Some(CreateTreeRequest { nodes, root_id })
+    }
+
+    // Builds the derivation of the stored statement `id` as it reads in the names of
+    // `statement`.
+    fn build(
+        &self,
+        id: Uuid,
+        statement: Statement,
+        sizes: &BTreeMap<Uuid, usize>,
+        branch: &mut BTreeSet<Uuid>,
+        nodes: &mut Vec<Node>,
+    ) -> Option<Uuid> {
+        let size = *sizes.get(&id)?;
+        // The premisses of a shortest derivation are smaller, the guard is for safety.
+        if !branch.insert(id) {
+            return None;
+        }
+        let derivation = self.derivations.get(&id)?.iter().find(|derivation| {
+            derivation
+                .premisses
+                .iter()
+                .map(|premiss| sizes.get(premiss).copied())
+                .sum::<Option<usize>>()
+                .is_some_and(|premisses| premisses + 1 == size)
+        })?;
+        // The premisses are renamed from the names of the stored conclusion to those of
+        // `statement`, both share the same normal form.
+        let (_, mut to_normal) = self.statements.get(&id)?.normalize();
+        let (_, from_normal) = statement.normalize();
+        let mut from_normal = from_normal.inverse();
+        let premisses = derivation
+            .premisses
+            .iter()
+            .zip(derivation.statements.iter())
+            .map(|(premiss, stored)| {
+                let renamed = from_normal.statement(&to_normal.statement(stored));
+                self.build(*premiss, renamed, sizes, branch, nodes)
+            })
+            .collect::<Option<Vec<_>>>()?;
+        branch.remove(&id);
+
+        let name = Uuid::new_v4();
+        nodes.push(Node {
+            name,
+            statement,
+            rule: derivation.rule.clone(),
+            premisses,
+        });
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::derivation::tree::check_tree;
+    use crate::lib::LogicParser;
+
+    fn statement(lhs: &[&str], formula: &str) -> Statement {
+        Statement {
+            lhs: lhs
+                .iter()
+                .map(|f| LogicParser::parse_input(f).unwrap())
+                .collect(),
+            formula: LogicParser::parse_input(formula).unwrap(),
+            sidecondition: vec![],
+        }
+    }
+
+    fn axiom() -> Vec<Derivation> {
+        vec![Derivation {
+            rule: Rules::Ax,
+            premisses: vec![],
+            statements: vec![],
+        }]
+    }
+
+    fn and_intro(premisses: Vec<(Uuid, Statement)>) -> Vec<Derivation> {
+        vec![Derivation {
+            rule: Rules::AndIntro,
+            premisses: premisses.iter().map(|(id, _)| *id).collect(),
+            statements: premisses.into_iter().map(|(_, s)| s).collect(),
+        }]
+    }
+
+    #[test]
+    fn shortest_derivation_renames_stored_premisses() {
+        let ids = (0..5).map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+        let graph = HintGraph {
+            root: ids[0],
+            statements: BTreeMap::from([
+                (ids[0], statement(&["A", "B"], "(B and A) and A")),
+                // The first premiss is stored in names of its own.
+                (ids[1], statement(&["B", "A"], "A and B")),
+                (ids[2], statement(&["A", "B"], "A")),
+                (ids[3], statement(&["B", "A"], "A")),
+                (ids[4], statement(&["B", "A"], "B")),
+            ]),
+            derivations: BTreeMap::from([
+                (
+                    ids[0],
+                    and_intro(vec![
+                        (ids[1], statement(&["A", "B"], "B and A")),
+                        (ids[2], statement(&["A", "B"], "A")),
+                    ]),
+                ),
+                (
+                    ids[1],
+                    and_intro(vec![
+                        (ids[3], statement(&["B", "A"], "A")),
+                        (ids[4], statement(&["B", "A"], "B")),
+                    ]),
+                ),
+                (ids[2], axiom()),
+                (ids[3], axiom()),
+                (ids[4], axiom()),
+            ]),
+        };
+
+        let tree = graph.shortest_derivation().unwrap();
+        assert_eq!(tree.nodes.len(), 5);
+        check_tree(tree.root_id, &tree.nodes).unwrap();
+    }
+}
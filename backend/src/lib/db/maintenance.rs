@@ -0,0 +1,382 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+};
+use uuid::Uuid;
+
+use crate::api::models::{HintGraphCheckParams, HintGraphReport, InvalidDerivation, Node};
+use crate::db::sea_orm_active_enums::Rules as DbRules;
+use crate::db::{exercise, node, statement};
+use crate::error::BackendResult;
+use crate::lib::derivation::statement::Statement;
+use crate::lib::derivation::tree::{check_node, NodeError};
+use crate::lib::rule::Rules;
+
+use super::{find_statement, insert_statement, parse_premiss, parse_statement, statement_columns};
+
+/// What `repair_sideconditions` changed.
+#[derive(Debug, Default)]
+pub struct SideConditionRepair {
+    /// Statements whose columns were brought into canonical form.
+    pub canonicalized: usize,
+    /// Statements merged into an equal one.
+    pub merged: usize,
+    /// Edges moved to a premiss with the side conditions of their conclusion.
+    pub moved: usize,
+    /// Edges removed because the rule is not sound without side conditions.
+    pub removed: usize,
+}
+
+// Moves everything referring to `duplicate` over to `survivor`. A conclusion keeps one
+// derivation per rule, so the ones of the survivor win.
+async fn merge_statement(
+    trx: &impl ConnectionTrait,
+    duplicate: Uuid,
+    survivor: Uuid,
+) -> BackendResult<()> {
+    let rules = node::Entity::find()
+        .filter(node::Column::ParentId.eq(survivor))
+        .all(trx)
+        .await?
+        .into_iter()
+        .map(|edge| edge.rule)
+        .collect::<Vec<_>>();
+    node::Entity::delete_many()
+        .filter(node::Column::ParentId.eq(duplicate))
+        .filter(node::Column::Rule.is_in(rules))
+        .exec(trx)
+        .await?;
+    node::Entity::update_many()
+        .col_expr(node::Column::ParentId, Expr::value(survivor))
+        .filter(node::Column::ParentId.eq(duplicate))
+        .exec(trx)
+        .await?;
+    node::Entity::update_many()
+        .col_expr(node::Column::ChildId, Expr::value(survivor))
+        .filter(node::Column::ChildId.eq(duplicate))
+        .exec(trx)
+        .await?;
+    exercise::Entity::update_many()
+        .col_expr(exercise::Column::StatementId, Expr::value(survivor))
+        .filter(exercise::Column::StatementId.eq(duplicate))
+        .exec(trx)
+        .await?;
+    statement::Entity::delete_by_id(duplicate).exec(trx).await?;
+    Ok(())
+}
+
+async fn copy_edges(trx: &impl ConnectionTrait, from: Uuid, to: Uuid) -> BackendResult<()> {
+    let edges = node::Entity::find()
+        .filter(node::Column::ParentId.eq(from))
+        .all(trx)
+        .await?;
+    for edge in edges {
+        node::ActiveModel {
+            parent_id: sea_orm::ActiveValue::Set(to),
+            child_id: sea_orm::ActiveValue::Set(edge.child_id),
+            rule: sea_orm::ActiveValue::Set(edge.rule),
+            premiss: sea_orm::ActiveValue::Set(edge.premiss),
+            order: sea_orm::ActiveValue::Set(edge.order),
+            uses: sea_orm::ActiveValue::Set(edge.uses),
+            size: sea_orm::ActiveValue::Set(edge.size),
+            derivation: sea_orm::ActiveValue::Set(edge.derivation),
+            ..Default::default()
+        }
+        .save(trx)
+        .await?;
+    }
+    Ok(())
+}
+
+// Checks a stored derivation as an application of the rule to the conclusion.
+fn check_derivation(
+    parent_id: Uuid,
+    conclusion: &Statement,
+    rule: &Rules,
+    premisses: Vec<(Uuid, Statement)>,
+) -> Result<(), NodeError> {
+    let node = Node {
+        name: parent_id,
+        statement: conclusion.clone(),
+        rule: rule.clone(),
+        premisses: premisses.iter().map(|(id, _)| *id).collect(),
+    };
+    let nodes = premisses
+        .into_iter()
+        .map(|(name, statement)| Node {
+            name,
+            statement,
+            rule: Rules::Ax,
+            premisses: Vec::new(),
+        })
+        .collect();
+    check_node(&node, &nodes)
+}
+
+async fn delete_derivation(
+    trx: &impl ConnectionTrait,
+    parent_id: Uuid,
+    rule: &Rules,
+    derivation: Option<Uuid>,
+) -> BackendResult<()> {
+    let derivation = match derivation {
+        Some(derivation) => node::Column::Derivation.eq(derivation),
+        None => node::Column::Derivation.is_null(),
+    };
+    node::Entity::delete_many()
+        .filter(node::Column::ParentId.eq(parent_id))
+        .filter(node::Column::Rule.eq::<DbRules>(rule.clone().into()))
+        .filter(derivation)
+        .exec(trx)
+        .await?;
+    Ok(())
+}
+
+/// Repairs the hint graph stored while side conditions were not part of the identity of
+/// a statement: equal statements are merged, premisses get the side conditions of their
+/// conclusion and derivations that are only sound with side conditions are removed.
+pub async fn repair_sideconditions(
+    trx: &impl ConnectionTrait,
+) -> BackendResult<SideConditionRepair> {
+    let mut report = SideConditionRepair::default();
+
+    let mut identities = BTreeMap::<(String, String, String), Uuid>::new();
+    for row in statement::Entity::find().all(trx).await? {
+        let columns = statement_columns(&parse_statement(&row)?)?;
+        if let Some(survivor) = identities.get(&columns) {
+            merge_statement(trx, row.id, *survivor).await?;
+            report.merged += 1;
+            continue;
+        }
+        identities.insert(columns.clone(), row.id);
+        if columns != (row.lhs.clone(), row.rhs.clone(), row.sidecondition.clone()) {
+            let (lhs, rhs, sidecondition) = columns;
+            let mut active_model = row.into_active_model();
+            active_model.lhs = sea_orm::ActiveValue::Set(lhs);
+            active_model.rhs = sea_orm::ActiveValue::Set(rhs);
+            active_model.sidecondition = sea_orm::ActiveValue::Set(sidecondition);
+            active_model.update(trx).await?;
+            report.canonicalized += 1;
+        }
+    }
+
+    // Rules keep the side conditions, so the premisses need those of the conclusion.
+    let mut pending = statement::Entity::find()
+        .filter(statement::Column::Sidecondition.ne("[]"))
+        .all(trx)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect::<VecDeque<_>>();
+    let mut visited = BTreeSet::new();
+    while let Some(id) = pending.pop_front() {
+        if !visited.insert(id) {
+            continue;
+        }
+        let Some(conclusion) = statement::Entity::find_by_id(id).one(trx).await? else {
+            continue;
+        };
+        let edges = node::Entity::find()
+            .filter(node::Column::ParentId.eq(id))
+            .all(trx)
+            .await?;
+        for edge in edges {
+            let Some(child_id) = edge.child_id else {
+                continue;
+            };
+            let Some(child) = statement::Entity::find_by_id(child_id).one(trx).await? else {
+                continue;
+            };
+            // Edges keeping their premiss were stored with the side conditions already.
+            if edge.premiss.is_some() || child.sidecondition == conclusion.sidecondition {
+                pending.push_back(child_id);
+                continue;
+            }
+            let mut premiss = parse_statement(&child)?;
+            premiss.sidecondition = parse_statement(&conclusion)?.sidecondition;
+            let target = match find_statement(trx, &premiss).await? {
+                Some(existing) => existing.id,
+                None => {
+                    let created = insert_statement(trx, &premiss).await?;
+                    copy_edges(trx, child_id, created.id).await?;
+                    created.id
+                }
+            };
+            let mut active_model = edge.into_active_model();
+            active_model.child_id = sea_orm::ActiveValue::Set(Some(target));
+            active_model.update(trx).await?;
+            report.moved += 1;
+            pending.push_back(target);
+        }
+    }
+
+    // The rules with a side condition may have been stored for a conclusion without it.
+    for rule in [Rules::ForallIntro, Rules::ExistsElim] {
+        let mut by_conclusion = BTreeMap::<(Uuid, Option<Uuid>), Vec<node::Model>>::new();
+        let edges = node::Entity::find()
+            .filter(node::Column::Rule.eq::<DbRules>(rule.clone().into()))
+            .all(trx)
+            .await?;
+        for edge in edges {
+            by_conclusion
+                .entry((edge.parent_id, edge.derivation))
+                .or_default()
+                .push(edge);
+        }
+
+        for ((parent_id, derivation), mut edges) in by_conclusion {
+            let Some(conclusion) = statement::Entity::find_by_id(parent_id).one(trx).await? else {
+                continue;
+            };
+            edges.sort_by_key(|edge| edge.order);
+            let mut premisses = Vec::new();
+            for edge in edges.iter() {
+                let Some(child_id) = edge.child_id else {
+                    continue;
+                };
+                if let Some(premiss) = parse_premiss(edge)? {
+                    premisses.push((child_id, premiss));
+                } else if let Some(child) = statement::Entity::find_by_id(child_id).one(trx).await?
+                {
+                    premisses.push((child_id, parse_statement(&child)?));
+                }
+            }
+            let conclusion = parse_statement(&conclusion)?;
+            if check_derivation(parent_id, &conclusion, &rule, premisses).is_err() {
+                delete_derivation(trx, parent_id, &rule, derivation).await?;
+                report.removed += edges.len();
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// Collects the cycles through `id`, each as the statements along it.
+fn find_cycles(
+    id: Uuid,
+    children: &BTreeMap<Uuid, BTreeSet<Uuid>>,
+    finished: &mut BTreeSet<Uuid>,
+    branch: &mut Vec<Uuid>,
+    cycles: &mut Vec<Vec<Uuid>>,
+) {
+    if finished.contains(&id) {
+        return;
+    }
+    if let Some(start) = branch.iter().position(|on_branch| *on_branch == id) {
+        cycles.push(branch[start..].to_vec());
+        return;
+    }
+    branch.push(id);
+    for child in children.get(&id).into_iter().flatten() {
+        find_cycles(*child, children, finished, branch, cycles);
+    }
+    branch.pop();
+    finished.insert(id);
+}
+
+/// Checks that every stored derivation is a valid rule application, looks for cycles and
+/// for statements no exercise reaches. Invalid derivations and unreachable statements are
+/// only deleted if asked for.
+pub async fn check_hint_graph(
+    trx: &impl ConnectionTrait,
+    params: &HintGraphCheckParams,
+) -> BackendResult<HintGraphReport> {
+    let mut report = HintGraphReport::default();
+
+    let statements = statement::Entity::find()
+        .all(trx)
+        .await?
+        .iter()
+        .map(|row| Ok((row.id, parse_statement(row)?)))
+        .collect::<BackendResult<BTreeMap<_, _>>>()?;
+    let mut derivations = BTreeMap::<(Uuid, Rules, Option<Uuid>), Vec<node::Model>>::new();
+    for edge in node::Entity::find().all(trx).await? {
+        derivations
+            .entry((edge.parent_id, edge.rule.clone().into(), edge.derivation))
+            .or_default()
+            .push(edge);
+    }
+
+    let mut invalid = Vec::new();
+    for ((parent_id, rule, derivation), edges) in derivations.iter_mut() {
+        edges.sort_by_key(|edge| edge.order);
+        report.checked += 1;
+        let Some(conclusion) = statements.get(parent_id) else {
+            continue;
+        };
+        let mut premisses = Vec::new();
+        for edge in edges.iter() {
+            let Some(id) = edge.child_id else {
+                continue;
+            };
+            if let Some(premiss) = parse_premiss(edge)?.or_else(|| statements.get(&id).cloned()) {
+                premisses.push((id, premiss));
+            }
+        }
+        if let Err(error) = check_derivation(*parent_id, conclusion, rule, premisses) {
+            report.invalid.push(InvalidDerivation {
+                statement: *parent_id,
+                rule: rule.clone(),
+                message: error.message,
+            });
+            invalid.push((*parent_id, rule.clone(), *derivation));
+        }
+    }
+    if params.delete_invalid {
+        for (parent_id, rule, derivation) in invalid {
+            delete_derivation(trx, parent_id, &rule, derivation).await?;
+            derivations.remove(&(parent_id, rule, derivation));
+        }
+    }
+
+    let mut children = BTreeMap::<Uuid, BTreeSet<Uuid>>::new();
+    for ((parent_id, _, _), edges) in derivations.iter() {
+        children
+            .entry(*parent_id)
+            .or_default()
+            .extend(edges.iter().filter_map(|edge| edge.child_id));
+    }
+    let mut finished = BTreeSet::new();
+    for id in statements.keys() {
+        find_cycles(
+            *id,
+            &children,
+            &mut finished,
+            &mut Vec::new(),
+            &mut report.cycles,
+        );
+    }
+
+    let mut reachable = BTreeSet::new();
+    let mut pending = exercise::Entity::find()
+        .all(trx)
+        .await?
+        .into_iter()
+        .map(|exercise| exercise.statement_id)
+        .collect::<Vec<_>>();
+    while let Some(id) = pending.pop() {
+        if reachable.insert(id) {
+            pending.extend(children.get(&id).into_iter().flatten());
+        }
+    }
+    report.unreachable = statements
+        .keys()
+        .filter(|id| !reachable.contains(id))
+        .cloned()
+        .collect();
+    if params.remove_unreachable && !report.unreachable.is_empty() {
+        node::Entity::delete_many()
+            .filter(node::Column::ParentId.is_in(report.unreachable.clone()))
+            .exec(trx)
+            .await?;
+        statement::Entity::delete_many()
+            .filter(statement::Column::Id.is_in(report.unreachable.clone()))
+            .exec(trx)
+            .await?;
+    }
+
+    Ok(report)
+}
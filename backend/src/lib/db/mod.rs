@@ -1,94 +1,287 @@
+pub mod graph;
+pub mod maintenance;
 mod utils;
+use std::collections::BTreeMap;
+
 use crate::api::models::Node;
 use crate::db::node;
+use crate::db::proof_session;
 use crate::db::sea_orm_active_enums::Rules as DbRules;
 use crate::db::statement;
 use crate::error::{BackendError, BackendResult};
-use crate::lib::rule::Rules;
+use crate::lib::derivation::statement::Statement;
+use crate::lib::session::ProofState;
 use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, IntoActiveModel};
 use sea_orm::{ConnectionTrait, EntityTrait, QueryFilter};
 use uuid::Uuid;
 
+// The columns identifying a statement. Side conditions are part of the identity, in a
+// canonical order.
+fn statement_columns(statement: &Statement) -> BackendResult<(String, String, String)> {
+    let mut sidecondition = statement.sidecondition.clone();
+    sidecondition.sort();
+    sidecondition.dedup();
+    let serialize = |value: serde_json::Result<String>| {
+        value.map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))
+    };
+    Ok((
+        serialize(serde_json::to_string(&statement.lhs))?,
+        serialize(serde_json::to_string(&statement.formula))?,
+        serialize(serde_json::to_string(&sidecondition))?,
+    ))
+}
+
+pub fn parse_statement(statement: &statement::Model) -> BackendResult<Statement> {
+    let deserialize = |e: serde_json::Error| {
+        BackendError::Unknown(format!(
+            "failed to deserialize statement {}: {e}",
+            statement.id
+        ))
+    };
+    Ok(Statement {
+        lhs: serde_json::from_str(&statement.lhs).map_err(deserialize)?,
+        formula: serde_json::from_str(&statement.rhs).map_err(deserialize)?,
+        sidecondition: serde_json::from_str(&statement.sidecondition).map_err(deserialize)?,
+    })
+}
+
+/// The premiss of a stored derivation in the names of its conclusion, `None` for edges
+/// stored before premisses were kept. Those use the names of the conclusion for the child.
+pub fn parse_premiss(edge: &node::Model) -> BackendResult<Option<Statement>> {
+    edge.premiss
+        .as_ref()
+        .map(|premiss| {
+            serde_json::from_str(premiss).map_err(|e| {
+                BackendError::Unknown(format!(
+                    "failed to deserialize the premiss of {}: {e}",
+                    edge.id
+                ))
+            })
+        })
+        .transpose()
+}
+
+pub async fn find_statement(
+    trx: &impl ConnectionTrait,
+    statement: &Statement,
+) -> BackendResult<Option<statement::Model>> {
+    let (lhs, rhs, sidecondition) = statement_columns(statement)?;
+
+    let statement = statement::Entity::find()
+        .filter(
+            Condition::all()
+                .add(statement::Column::Lhs.eq(&lhs))
+                .add(statement::Column::Rhs.eq(&rhs))
+                .add(statement::Column::Sidecondition.eq(&sidecondition)),
+        )
+        .one(trx)
+        .await?;
+    Ok(statement)
+}
+
+pub async fn insert_statement(
+    trx: &impl ConnectionTrait,
+    statement: &Statement,
+) -> BackendResult<statement::Model> {
+    let (lhs, rhs, sidecondition) = statement_columns(statement)?;
+    let statement = statement::ActiveModel {
+        lhs: sea_orm::ActiveValue::Set(lhs),
+        rhs: sea_orm::ActiveValue::Set(rhs),
+        sidecondition: sea_orm::ActiveValue::Set(sidecondition),
+        ..Default::default()
+    };
+    Ok(statement.insert(trx).await?)
+}
+
+/// Stores the derivation in the hint graph. Every statement is stored in its own normal
+/// form, so hints are found for each goal of the derivation.
 pub async fn add_tree(
     trx: &impl ConnectionTrait,
     root_id: Uuid,
-    nodes: &Vec<Node>,
+    nodes: &[Node],
 ) -> BackendResult<Uuid> {
     println!("Adding tree with root_id: {}", root_id);
-    let node = nodes.iter().find(|n| n.name == root_id).unwrap();
+    let (statement_id, _) = add_subtree(trx, root_id, nodes).await?;
+    Ok(statement_id)
+}
 
-    let lhs = serde_json::to_string(&node.statement.lhs)
-        .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
-    let rhs = serde_json::to_string(&node.statement.formula)
-        .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
+// The number of rules of the shortest stored derivation of the statement.
+async fn shortest_derivation(
+    trx: &impl ConnectionTrait,
+    statement_id: Uuid,
+) -> BackendResult<Option<i32>> {
+    let derivations = node::Entity::find()
+        .filter(node::Column::ParentId.eq(statement_id))
+        .all(trx)
+        .await?;
+    Ok(derivations.iter().map(|node| node.size).min())
+}
 
-    let statement = Box::pin(
-        statement::Entity::find()
-            .filter(
-                Condition::all()
-                    .add(statement::Column::Lhs.eq(&lhs))
-                    .add(statement::Column::Rhs.eq(&rhs)),
-            )
-            .one(trx),
-    )
-    .await?;
+// Stores the subtree and returns the id of its statement together with the size of its
+// shortest stored derivation. A derivation that is already stored, i.e. with the same rule
+// and premisses, is counted as used. Any other one is stored next to it.
+async fn add_subtree(
+    trx: &impl ConnectionTrait,
+    root_id: Uuid,
+    nodes: &[Node],
+) -> BackendResult<(Uuid, i32)> {
+    let node = nodes.iter().find(|n| n.name == root_id).unwrap();
+
+    // The premisses are renamed along with the conclusion and stored on the edges.
+    let (normalized, mut normalizer) = node.statement.normalize();
+    let statement = Box::pin(find_statement(trx, &normalized)).await?;
 
     let statement_id = match statement {
         Some(s) => s.id,
-        None => {
-            let statement = statement::ActiveModel {
-                lhs: sea_orm::ActiveValue::Set(lhs),
-                rhs: sea_orm::ActiveValue::Set(rhs),
-                ..Default::default()
-            };
-            let added = Box::pin(statement.save(trx)).await?;
-            let res = added.id.unwrap();
-            res
-        }
+        None => Box::pin(insert_statement(trx, &normalized)).await?.id,
     };
 
-    let already_exists = Box::pin(
+    // Each premiss in the names of the conclusion and the stored statement of its own
+    // normal form, if there is one.
+    let mut premisses = Vec::new();
+    for child in node.premisses.iter() {
+        let submitted = nodes
+            .iter()
+            .find(|n| n.name == *child)
+            .ok_or_else(|| BackendError::BadRequest("Could not find all premisses".to_string()))?;
+        let (own, _) = submitted.statement.normalize();
+        let stored = Box::pin(find_statement(trx, &own)).await?;
+        premisses.push((
+            normalizer.statement(&submitted.statement),
+            stored.map(|s| s.id),
+        ));
+    }
+
+    let stored = Box::pin(
         node::Entity::find()
             .filter(
                 Condition::all()
                     .add(node::Column::ParentId.eq(statement_id))
                     .add(node::Column::Rule.eq::<DbRules>(node.rule.clone().into())),
             )
-            .one(trx),
+            .all(trx),
     )
     .await?;
+    let mut derivations = BTreeMap::<Option<Uuid>, Vec<node::Model>>::new();
+    for edge in stored {
+        derivations.entry(edge.derivation).or_default().push(edge);
+    }
+    let mut known = None;
+    for (_, mut edges) in derivations {
+        edges.sort_by_key(|edge| edge.order);
+        if same_premisses(&edges, &premisses)? {
+            known = Some(edges);
+            break;
+        }
+    }
+
+    let mut size = 1;
+    let mut children = Vec::new();
+    for (child, (premiss, _)) in node.premisses.iter().zip(premisses) {
+        let (child_id, child_size) = Box::pin(add_subtree(trx, *child, nodes)).await?;
+        children.push((child_id, premiss));
+        size += child_size;
+    }
 
-    match already_exists {
-        Some(node) => {
-            return Ok(node.parent_id);
+    if let Some(edges) = known {
+        for edge in edges {
+            let uses = edge.uses + 1;
+            let shortest = edge.size.min(size);
+            let mut active_model = edge.into_active_model();
+            active_model.uses = sea_orm::ActiveValue::Set(uses);
+            active_model.size = sea_orm::ActiveValue::Set(shortest);
+            let _ = Box::pin(active_model.update(trx)).await?;
         }
-        None => {
-            if node.rule == Rules::Ax {
-                let node = node::ActiveModel {
-                    parent_id: sea_orm::ActiveValue::Set(statement_id),
-                    rule: sea_orm::ActiveValue::Set(node.rule.clone().into()),
-                    order: sea_orm::ActiveValue::Set(0),
-                    ..Default::default()
-                };
-                let _ = Box::pin(node.save(trx)).await?;
-                return Ok(statement_id);
-            }
-
-            for (i, child) in node.premisses.clone().into_iter().enumerate() {
-                let child_id = Box::pin(add_tree(trx, child, nodes)).await?;
-
-                let node = node::ActiveModel {
-                    child_id: sea_orm::ActiveValue::Set(Some(child_id)),
-                    parent_id: sea_orm::ActiveValue::Set(statement_id),
-                    rule: sea_orm::ActiveValue::Set(node.rule.clone().into()),
-                    order: sea_orm::ActiveValue::Set(i as i32),
-                    ..Default::default()
-                };
-
-                let _ = Box::pin(node.save(trx)).await?;
-            }
-
-            Ok(statement_id)
+    } else if children.is_empty() {
+        // Rules without premisses, e.g. `Ax` or `Lem`, are stored as a single leaf edge.
+        let node = node::ActiveModel {
+            parent_id: sea_orm::ActiveValue::Set(statement_id),
+            rule: sea_orm::ActiveValue::Set(node.rule.clone().into()),
+            order: sea_orm::ActiveValue::Set(0),
+            uses: sea_orm::ActiveValue::Set(1),
+            size: sea_orm::ActiveValue::Set(size),
+            derivation: sea_orm::ActiveValue::Set(Some(Uuid::new_v4())),
+            ..Default::default()
+        };
+        let _ = Box::pin(node.save(trx)).await?;
+    } else {
+        let derivation = Uuid::new_v4();
+        for (i, (child_id, premiss)) in children.into_iter().enumerate() {
+            let premiss = serde_json::to_string(&premiss)
+                .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
+            let node = node::ActiveModel {
+                child_id: sea_orm::ActiveValue::Set(Some(child_id)),
+                premiss: sea_orm::ActiveValue::Set(Some(premiss)),
+                parent_id: sea_orm::ActiveValue::Set(statement_id),
+                rule: sea_orm::ActiveValue::Set(node.rule.clone().into()),
+                order: sea_orm::ActiveValue::Set(i as i32),
+                uses: sea_orm::ActiveValue::Set(1),
+                size: sea_orm::ActiveValue::Set(size),
+                derivation: sea_orm::ActiveValue::Set(Some(derivation)),
+                ..Default::default()
+            };
+
+            let _ = Box::pin(node.save(trx)).await?;
         }
     }
+
+    let shortest = Box::pin(shortest_derivation(trx, statement_id))
+        .await?
+        .unwrap_or(size);
+    Ok((statement_id, shortest))
+}
+
+// Whether the stored edges, sorted by their order, derive the conclusion from the
+// submitted premisses.
+fn same_premisses(
+    edges: &[node::Model],
+    premisses: &[(Statement, Option<Uuid>)],
+) -> BackendResult<bool> {
+    let edges = edges
+        .iter()
+        .filter(|edge| edge.child_id.is_some())
+        .collect::<Vec<_>>();
+    if edges.len() != premisses.len() {
+        return Ok(false);
+    }
+    for (edge, (premiss, stored)) in edges.iter().zip(premisses) {
+        if edge.child_id != *stored {
+            return Ok(false);
+        }
+        // Edges stored before premisses were kept only know their child.
+        if parse_premiss(edge)?.is_some_and(|stored| stored != *premiss) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+pub async fn find_session(
+    trx: &impl ConnectionTrait,
+    id: Uuid,
+) -> BackendResult<(proof_session::Model, ProofState)> {
+    let session = proof_session::Entity::find_by_id(id)
+        .one(trx)
+        .await?
+        .ok_or_else(|| BackendError::IdNotFound {
+            entity: "ProofSession".to_string(),
+            id: id.to_string(),
+        })?;
+    let state = serde_json::from_str::<ProofState>(&session.state)
+        .map_err(|e| BackendError::Unknown(format!("failed to deserialize: {e}")))?;
+    Ok((session, state))
+}
+
+pub async fn save_session(
+    trx: &impl ConnectionTrait,
+    session: proof_session::Model,
+    state: &ProofState,
+) -> BackendResult<proof_session::Model> {
+    let state = serde_json::to_string(state)
+        .map_err(|e| BackendError::BadRequest(format!("failed to serialize: {e}")))?;
+    let completed = session.completed;
+    let mut active_model = session.into_active_model();
+    active_model.state = sea_orm::ActiveValue::Set(state);
+    active_model.completed = sea_orm::ActiveValue::Set(completed);
+    Ok(active_model.update(trx).await?)
 }
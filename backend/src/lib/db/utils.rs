@@ -22,6 +22,11 @@ impl From<Rules> for DbRules {
             Rules::ExistsIntro => DbRules::ExistsIntro,
             Rules::AlphaExists => DbRules::AlphaExists,
             Rules::AlphaForall => DbRules::AlphaForall,
+            Rules::EqIntro => DbRules::EqIntro,
+            Rules::EqElim => DbRules::EqElim,
+            Rules::Raa => DbRules::Raa,
+            Rules::Lem => DbRules::Lem,
+            Rules::DoubleNegElim => DbRules::DoubleNegElim,
         }
     }
 }
@@ -47,6 +52,11 @@ impl From<DbRules> for Rules {
             DbRules::ExistsIntro => Rules::ExistsIntro,
             DbRules::AlphaExists => Rules::AlphaExists,
             DbRules::AlphaForall => Rules::AlphaForall,
+            DbRules::EqIntro => Rules::EqIntro,
+            DbRules::EqElim => Rules::EqElim,
+            DbRules::Raa => Rules::Raa,
+            DbRules::Lem => Rules::Lem,
+            DbRules::DoubleNegElim => Rules::DoubleNegElim,
         }
     }
 }
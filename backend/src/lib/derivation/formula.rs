@@ -15,6 +15,15 @@ pub enum Identifier {
     Element(String),
 }
 
+/// Arguments of predicates and equalities. Constants are written as free elements.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(tag = "type", content = "value")]
+#[schema(no_recursion)]
+pub enum Term {
+    Element(String),
+    Function { name: String, args: Vec<Term> },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(tag = "type", content = "body")]
 #[schema(no_recursion)]
@@ -45,111 +54,164 @@ pub enum Formula {
     },
     Predicate {
         identifier: Identifier,
-        identifiers: Vec<Identifier>,
+        identifiers: Vec<Term>,
+    },
+    Eq {
+        lhs: Term,
+        rhs: Term,
     },
 }
 
+impl Term {
+    pub fn apply_substitution(&self, from: &str, to: &str) -> Term {
+        match self {
+            Term::Element(s) if s == from => Term::Element(to.to_string()),
+            Term::Element(s) => Term::Element(s.to_string()),
+            Term::Function { name, args } => Term::Function {
+                name: name.clone(),
+                args: args
+                    .iter()
+                    .map(|arg| arg.apply_substitution(from, to))
+                    .collect(),
+            },
+        }
+    }
+
+    /// All elements occurring in the term.
+    pub fn elements(&self) -> BTreeSet<String> {
+        match self {
+            Term::Element(s) => BTreeSet::from([s.clone()]),
+            Term::Function { args, .. } => args.iter().flat_map(|arg| arg.elements()).collect(),
+        }
+    }
+}
+
+/// Appends primes to the name until it differs from all names to avoid.
+pub fn fresh_name(name: &str, avoid: &BTreeSet<String>) -> String {
+    let mut fresh = format!("{name}'");
+    while avoid.contains(&fresh) {
+        fresh.push('\'');
+    }
+    fresh
+}
+
 impl Formula {
-    pub fn apply_substitution(
-        &self,
-        from: &String,
-        to: &String,
-        captured: BTreeSet<String>,
-    ) -> BackendResult<Formula> {
+    /// Replaces the free occurrences of the element `from` by `to`. A quantifier that
+    /// would capture `to` is renamed to a fresh element first.
+    pub fn apply_substitution(&self, from: &String, to: &String) -> BackendResult<Formula> {
         match self {
             Formula::And { lhs, rhs } => Ok(Formula::And {
-                lhs: Box::new(lhs.apply_substitution(from, to, captured.clone())?),
-                rhs: Box::new(rhs.apply_substitution(from, to, captured.clone())?),
+                lhs: Box::new(lhs.apply_substitution(from, to)?),
+                rhs: Box::new(rhs.apply_substitution(from, to)?),
             }),
             Formula::Or { lhs, rhs } => Ok(Formula::Or {
-                lhs: Box::new(lhs.apply_substitution(from, to, captured.clone())?),
-                rhs: Box::new(rhs.apply_substitution(from, to, captured.clone())?),
+                lhs: Box::new(lhs.apply_substitution(from, to)?),
+                rhs: Box::new(rhs.apply_substitution(from, to)?),
             }),
             Formula::Not(formula) => Ok(Formula::Not(Box::new(
-                formula.apply_substitution(from, to, captured)?,
+                formula.apply_substitution(from, to)?,
             ))),
-            Formula::Ident(identifier) => {
-                let new_name = match identifier.clone() {
-                    Identifier::Element(s) | Identifier::Literal(s) => {
-                        if s == *from {
-                            if captured.contains(to) {
-                                return Err(BackendError::BadRequest(
-                                    "Substitution results in Capture of variable".to_string(),
-                                ));
-                            }
-                            to.to_string()
-                        } else {
-                            s
-                        }
-                    }
-                };
-                match identifier {
-                    Identifier::Element(_) => Ok(Formula::Ident(Identifier::Element(new_name))),
-                    Identifier::Literal(_) => Ok(Formula::Ident(Identifier::Literal(new_name))),
-                }
+            Formula::Ident(Identifier::Element(s)) if s == from => {
+                Ok(Formula::Ident(Identifier::Element(to.to_string())))
             }
+            Formula::Ident(identifier) => Ok(Formula::Ident(identifier.clone())),
             Formula::Imp { lhs, rhs } => Ok(Formula::Imp {
-                lhs: Box::new(lhs.apply_substitution(from, to, captured.clone())?),
-                rhs: Box::new(rhs.apply_substitution(from, to, captured.clone())?),
+                lhs: Box::new(lhs.apply_substitution(from, to)?),
+                rhs: Box::new(rhs.apply_substitution(from, to)?),
             }),
             Formula::True => Ok(Formula::True),
             Formula::False => Ok(Formula::False),
             Formula::Forall {
                 identifier,
                 formula,
-            } => {
-                let name = match &identifier {
-                    Identifier::Element(s) | Identifier::Literal(s) => s,
-                };
-                let mut new_capture = captured.clone();
-                new_capture.insert(name.clone());
-                Ok(Formula::Forall {
-                    identifier: identifier.clone(),
-                    formula: Box::new(formula.apply_substitution(from, to, new_capture)?),
-                })
             }
-            Formula::Exists {
+            | Formula::Exists {
                 identifier,
                 formula,
             } => {
-                let name = match &identifier {
+                let name = match identifier {
                     Identifier::Element(s) => s,
                     Identifier::Literal(_) => Err(BackendError::BadRequest(
                         "The variable must be an simple element i.e. Not a formula.".to_string(),
                     ))?,
                 };
-                let mut new_capture = captured.clone();
-                new_capture.insert(name.clone());
-                Ok(Formula::Exists {
-                    identifier: identifier.clone(),
-                    formula: Box::new(formula.apply_substitution(from, to, new_capture)?),
+                let free = formula.free_vars(BTreeSet::new())?;
+                let (name, formula) = if name == from || !free.contains(from) {
+                    // The element is bound here, or does not occur at all.
+                    (name.clone(), *formula.clone())
+                } else if name == to {
+                    let mut avoid = formula.elements();
+                    avoid.extend([from.clone(), to.clone()]);
+                    let fresh = fresh_name(name, &avoid);
+                    let renamed = formula.apply_substitution(name, &fresh)?;
+                    (fresh, renamed.apply_substitution(from, to)?)
+                } else {
+                    (name.clone(), formula.apply_substitution(from, to)?)
+                };
+                let identifier = Identifier::Element(name);
+                let formula = Box::new(formula);
+                Ok(match self {
+                    Formula::Forall { .. } => Formula::Forall {
+                        identifier,
+                        formula,
+                    },
+                    _ => Formula::Exists {
+                        identifier,
+                        formula,
+                    },
                 })
             }
             Formula::Predicate {
                 identifier,
                 identifiers,
+            } => Ok(Formula::Predicate {
+                identifier: identifier.clone(),
+                identifiers: identifiers
+                    .iter()
+                    .map(|t| t.apply_substitution(from, to))
+                    .collect(),
+            }),
+            Formula::Eq { lhs, rhs } => Ok(Formula::Eq {
+                lhs: lhs.apply_substitution(from, to),
+                rhs: rhs.apply_substitution(from, to),
+            }),
+        }
+    }
+
+    /// All elements occurring in the formula, bound or free.
+    pub fn elements(&self) -> BTreeSet<String> {
+        match self {
+            Formula::And { lhs, rhs } | Formula::Or { lhs, rhs } | Formula::Imp { lhs, rhs } => {
+                let mut elements = lhs.elements();
+                elements.extend(rhs.elements());
+                elements
+            }
+            Formula::Not(formula) => formula.elements(),
+            Formula::Ident(Identifier::Element(s)) => BTreeSet::from([s.clone()]),
+            Formula::Ident(Identifier::Literal(_)) | Formula::True | Formula::False => {
+                BTreeSet::new()
+            }
+            Formula::Forall {
+                identifier,
+                formula,
+            }
+            | Formula::Exists {
+                identifier,
+                formula,
             } => {
-                let new_identifiers = identifiers
-                    .into_iter()
-                    .map(|i| match i {
-                        Identifier::Element(s) | Identifier::Literal(s) => {
-                            if s == from {
-                                if captured.contains(to) {
-                                    return Err(BackendError::BadRequest(
-                                        "Substitution results in Capture of variable".to_string(),
-                                    ));
-                                }
-                                Ok(Identifier::Element(to.to_string()))
-                            } else {
-                                Ok(Identifier::Element(s.to_string()))
-                            }
-                        }
-                    })
-                    .collect::<Result<Vec<Identifier>, BackendError>>()?;
-                Ok(Formula::Predicate {
-                    identifier: identifier.clone(),
-                    identifiers: new_identifiers,
-                })
+                let mut elements = formula.elements();
+                if let Identifier::Element(s) = identifier {
+                    elements.insert(s.clone());
+                }
+                elements
+            }
+            Formula::Predicate { identifiers, .. } => {
+                identifiers.iter().flat_map(|t| t.elements()).collect()
+            }
+            Formula::Eq { lhs, rhs } => {
+                let mut elements = lhs.elements();
+                elements.extend(rhs.elements());
+                elements
             }
         }
     }
@@ -174,6 +236,7 @@ impl Formula {
                 identifier,
                 identifiers,
             } => Ok(false),
+            Formula::Eq { .. } => Ok(false),
         }
     }
 
@@ -190,7 +253,8 @@ impl Formula {
             | Formula::Predicate {
                 identifier: _,
                 identifiers: _,
-            } => Ok(BTreeSet::new()),
+            }
+            | Formula::Eq { .. } => Ok(BTreeSet::new()),
             Formula::Ident(n) => {
                 let captrue_from_sc = side_con
                     .iter()
@@ -274,21 +338,135 @@ impl Formula {
                 }
                 formula.free_vars(new_captured)
             }
-            Formula::Predicate { identifiers, .. } => {
-                let free = identifiers
+            Formula::Predicate { identifiers, .. } => Ok(identifiers
+                .iter()
+                .flat_map(|t| t.elements())
+                .filter(|s| !captured.contains(s))
+                .collect()),
+            Formula::Eq { lhs, rhs } => Ok(lhs
+                .elements()
+                .union(&rhs.elements())
+                .filter(|s| !captured.contains(*s))
+                .cloned()
+                .collect()),
+        }
+    }
+}
+
+// Bound elements are compared by the position of their quantifier, free elements by
+// name.
+fn alpha_equivalent_terms(lhs: &Term, rhs: &Term, bound: &[(&str, &str)]) -> bool {
+    match (lhs, rhs) {
+        (Term::Element(l), Term::Element(r)) => {
+            let position_l = bound.iter().rposition(|(b, _)| b == l);
+            let position_r = bound.iter().rposition(|(_, b)| b == r);
+            match (position_l, position_r) {
+                (None, None) => l == r,
+                (position_l, position_r) => position_l == position_r,
+            }
+        }
+        (
+            Term::Function {
+                name: name_l,
+                args: args_l,
+            },
+            Term::Function {
+                name: name_r,
+                args: args_r,
+            },
+        ) => {
+            name_l == name_r
+                && args_l.len() == args_r.len()
+                && args_l
                     .iter()
-                    .filter_map(|i| match i {
-                        Identifier::Element(s) => {
-                            if captured.contains(s) {
-                                None
-                            } else {
-                                Some(s.to_string())
-                            }
-                        }
-                        Identifier::Literal(_) => None,
-                    })
-                    .collect::<BTreeSet<_>>();
-                Ok(free)
+                    .zip(args_r.iter())
+                    .all(|(l, r)| alpha_equivalent_terms(l, r, bound))
+        }
+        _ => false,
+    }
+}
+
+fn alpha_equivalent_bound<'a>(
+    lhs: &'a Formula,
+    rhs: &'a Formula,
+    bound: &mut Vec<(&'a str, &'a str)>,
+) -> bool {
+    match (lhs, rhs) {
+        (Formula::And { lhs: l1, rhs: r1 }, Formula::And { lhs: l2, rhs: r2 })
+        | (Formula::Or { lhs: l1, rhs: r1 }, Formula::Or { lhs: l2, rhs: r2 })
+        | (Formula::Imp { lhs: l1, rhs: r1 }, Formula::Imp { lhs: l2, rhs: r2 }) => {
+            alpha_equivalent_bound(l1, l2, bound) && alpha_equivalent_bound(r1, r2, bound)
+        }
+        (Formula::Not(l), Formula::Not(r)) => alpha_equivalent_bound(l, r, bound),
+        (
+            Formula::Forall {
+                identifier: Identifier::Element(x),
+                formula: l,
+            },
+            Formula::Forall {
+                identifier: Identifier::Element(y),
+                formula: r,
+            },
+        )
+        | (
+            Formula::Exists {
+                identifier: Identifier::Element(x),
+                formula: l,
+            },
+            Formula::Exists {
+                identifier: Identifier::Element(y),
+                formula: r,
+            },
+        ) => {
+            bound.push((x, y));
+            let equivalent = alpha_equivalent_bound(l, r, bound);
+            bound.pop();
+            equivalent
+        }
+        (
+            Formula::Predicate {
+                identifier: name_l,
+                identifiers: args_l,
+            },
+            Formula::Predicate {
+                identifier: name_r,
+                identifiers: args_r,
+            },
+        ) => {
+            name_l == name_r
+                && args_l.len() == args_r.len()
+                && args_l
+                    .iter()
+                    .zip(args_r.iter())
+                    .all(|(l, r)| alpha_equivalent_terms(l, r, bound))
+        }
+        (Formula::Eq { lhs: l1, rhs: r1 }, Formula::Eq { lhs: l2, rhs: r2 }) => {
+            alpha_equivalent_terms(l1, l2, bound) && alpha_equivalent_terms(r1, r2, bound)
+        }
+        (Formula::Ident(Identifier::Element(l)), Formula::Ident(Identifier::Element(r))) => {
+            alpha_equivalent_terms(&Term::Element(l.clone()), &Term::Element(r.clone()), bound)
+        }
+        (l, r) => l == r,
+    }
+}
+
+/// Whether the formulas are equal up to the names of bound elements, e.g.
+/// `forall_x p(x)` and `forall_y p(y)`.
+pub fn alpha_equivalent(lhs: &Formula, rhs: &Formula) -> bool {
+    lhs == rhs || alpha_equivalent_bound(lhs, rhs, &mut Vec::new())
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Term::Element(s) => write!(f, "{s}"),
+            Term::Function { name, args } => {
+                let args = args
+                    .iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                write!(f, "{name}({args})")
             }
         }
     }
@@ -307,41 +485,190 @@ impl Display for Identifier {
     }
 }
 
-impl Display for Formula {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Formula {
+    // Binding strength as used by the parser: `->` < `or` < `and` < `not`.
+    pub(crate) fn precedence(&self) -> u8 {
         match self {
-            Formula::And { lhs, rhs } => write!(f, "({lhs} and {rhs})"),
-            Formula::Or { lhs, rhs } => write!(f, "({lhs} or {rhs})"),
-            Formula::Not(formula) => write!(f, "(not {formula})"),
-            Formula::Ident(identifier) => match identifier {
-                Identifier::Literal(s) => write!(f, "{s}"),
-                Identifier::Element(s) => write!(f, "{s}"),
-            },
-            Formula::Imp { lhs, rhs } => write!(f, "({lhs} -> {rhs})"),
-            Formula::True => write!(f, "true"),
-            Formula::False => write!(f, "false"),
+            Formula::Imp { .. } => 1,
+            Formula::Or { .. } => 2,
+            Formula::And { .. } => 3,
+            Formula::Not(_) | Formula::Forall { .. } | Formula::Exists { .. } => 4,
+            Formula::Ident(_)
+            | Formula::True
+            | Formula::False
+            | Formula::Predicate { .. }
+            | Formula::Eq { .. } => 5,
+        }
+    }
+
+    // Writes the formula, adding parentheses if it binds weaker than required. The body
+    // of a quantifier extends as far to the right as possible, so a quantifier needs
+    // parentheses unless it ends the formula, i.e. is `rightmost`.
+    fn fmt_with(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        required: u8,
+        rightmost: bool,
+    ) -> std::fmt::Result {
+        let precedence = self.precedence();
+        let quantifier = matches!(self, Formula::Forall { .. } | Formula::Exists { .. });
+        let parentheses = precedence < required || (quantifier && !rightmost);
+        let rightmost = rightmost || parentheses;
+        if parentheses {
+            write!(f, "(")?;
+        }
+        match self {
+            Formula::And { lhs, rhs } => {
+                lhs.fmt_with(f, precedence, false)?;
+                write!(f, " and ")?;
+                rhs.fmt_with(f, precedence + 1, rightmost)?;
+            }
+            Formula::Or { lhs, rhs } => {
+                lhs.fmt_with(f, precedence, false)?;
+                write!(f, " or ")?;
+                rhs.fmt_with(f, precedence + 1, rightmost)?;
+            }
+            Formula::Imp { lhs, rhs } => {
+                lhs.fmt_with(f, precedence + 1, false)?;
+                write!(f, " -> ")?;
+                rhs.fmt_with(f, precedence, rightmost)?;
+            }
+            Formula::Not(formula) => {
+                write!(f, "not ")?;
+                formula.fmt_with(f, precedence, rightmost)?;
+            }
             Formula::Forall {
                 identifier,
                 formula,
-            } => write!(f, "(forall_{identifier} {formula})"),
+            } => {
+                write!(f, "forall_{identifier} ")?;
+                formula.fmt_with(f, 0, true)?;
+            }
             Formula::Exists {
                 identifier,
                 formula,
-            } => write!(f, "(exists_{identifier} {formula})"),
+            } => {
+                write!(f, "exists_{identifier} ")?;
+                formula.fmt_with(f, 0, true)?;
+            }
+            Formula::Ident(identifier) => write!(f, "{identifier}")?,
+            Formula::True => write!(f, "true")?,
+            Formula::False => write!(f, "false")?,
             Formula::Predicate {
                 identifier,
                 identifiers,
             } => {
-                let mut s = identifier.to_string();
-                s.push('(');
-                for i in identifiers {
-                    s.push_str(&i.to_string());
-                    s.push(',');
-                }
-                s.pop();
-                s.push(')');
-                write!(f, "{s}")
+                let args = identifiers
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                write!(f, "{identifier}({args})")?;
             }
+            Formula::Eq { lhs, rhs } => write!(f, "{lhs} = {rhs}")?,
+        }
+        if parentheses {
+            write!(f, ")")?;
         }
+        Ok(())
+    }
+}
+
+impl Display for Formula {
+    /// Prints the formula with as few parentheses as possible, such that parsing the
+    /// output yields the same formula again.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with(f, 0, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::LogicParser;
+
+    fn parse(formula: &str) -> Formula {
+        LogicParser::parse_input(formula).unwrap()
+    }
+
+    fn substitute(formula: &str, from: &str, to: &str) -> Formula {
+        parse(formula)
+            .apply_substitution(&from.to_string(), &to.to_string())
+            .unwrap()
+    }
+
+    #[test]
+    fn alpha_equivalence_ignores_bound_names() {
+        for (lhs, rhs) in [
+            ("forall_x p(x)", "forall_y p(y)"),
+            ("forall_x q(x, y)", "forall_z q(z, y)"),
+            ("forall_x exists_y q(x, y)", "forall_y exists_x q(y, x)"),
+            ("exists_x p(f(x, c))", "exists_z p(f(z, c))"),
+            ("forall_x (x = x)", "forall_y (y = y)"),
+            ("A and forall_x p(x)", "A and forall_y p(y)"),
+        ] {
+            assert!(alpha_equivalent(&parse(lhs), &parse(rhs)), "{lhs} ~ {rhs}");
+        }
+    }
+
+    #[test]
+    fn alpha_equivalence_keeps_free_names() {
+        for (lhs, rhs) in [
+            ("p(x)", "p(y)"),
+            ("forall_x q(x, y)", "forall_y q(y, y)"),
+            ("forall_x q(x, y)", "forall_z q(z, w)"),
+            ("forall_x exists_y q(x, y)", "forall_x exists_y q(y, x)"),
+            ("forall_x p(x)", "exists_x p(x)"),
+        ] {
+            assert!(!alpha_equivalent(&parse(lhs), &parse(rhs)), "{lhs} ~ {rhs}");
+        }
+    }
+
+    #[test]
+    fn substitution_replaces_free_occurrences() {
+        assert_eq!(
+            substitute("p(x) and forall_x p(x)", "x", "c"),
+            parse("p(c) and forall_x p(x)")
+        );
+        assert_eq!(
+            substitute("q(f(x), x) -> x = g(x)", "x", "c"),
+            parse("q(f(c), c) -> c = g(c)")
+        );
+        assert_eq!(
+            substitute("forall_y p(y)", "x", "c"),
+            parse("forall_y p(y)")
+        );
+    }
+
+    #[test]
+    fn equalities_only_relate_elements() {
+        assert!(parse("forall_x x = x").well_formed().is_ok());
+        assert!(parse("x = y -> p(f(x))").well_formed().is_ok());
+        for formula in ["f(c) = f(c)", "forall_x x = g(x)", "p(c) and f(c) = c"] {
+            let error = parse(formula).well_formed().unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains("only supported between elements"),
+                "{formula}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn substitution_avoids_capture() {
+        let substituted = substitute("forall_y q(x, y)", "x", "y");
+        assert!(alpha_equivalent(&substituted, &parse("forall_z q(y, z)")));
+        assert!(!alpha_equivalent(&substituted, &parse("forall_y q(y, y)")));
+        assert_eq!(
+            substituted.free_vars(BTreeSet::new()).unwrap(),
+            BTreeSet::from(["y".to_string()])
+        );
+
+        let substituted = substitute("exists_y (p(x) and forall_x q(x, y))", "x", "y");
+        assert!(alpha_equivalent(
+            &substituted,
+            &parse("exists_z (p(y) and forall_x q(x, z))")
+        ));
     }
 }
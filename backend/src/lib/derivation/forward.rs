@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    error::{BackendError, BackendResult},
+    lib::rule::{apply::get_formula, DerivationRule, RuleIdentifier},
+};
+
+use super::{
+    formula::{alpha_equivalent, Formula, Identifier},
+    statement::Statement,
+    tree::{element_substitution, infer_mapping_stmt},
+};
+
+pub fn contains(formulas: &[Formula], formula: &Formula) -> bool {
+    formulas.iter().any(|f| alpha_equivalent(f, formula))
+}
+
+/// Whether every assumption of `lhs` is also one of `rhs`.
+pub fn subset(lhs: &[Formula], rhs: &[Formula]) -> bool {
+    lhs.iter().all(|f| contains(rhs, f))
+}
+
+impl Statement {
+    /// Applies the rule top-down: the premisses are matched with the premisses of the
+    /// rule and the conclusion is returned. Rules without premisses, e.g. `Ax`, take the
+    /// assumptions of the conclusion from `context`.
+    pub fn derive(
+        rule: &DerivationRule,
+        premisses: &[Statement],
+        context: Option<&[Formula]>,
+        mapping: &mut BTreeMap<RuleIdentifier, Formula>,
+        substitution: &mut BTreeMap<RuleIdentifier, String>,
+    ) -> BackendResult<Statement> {
+        if premisses.len() != rule.premises.len() {
+            return Err(BackendError::BadRequest(format!(
+                "The rule expects {} premisses but {} were given",
+                rule.premises.len(),
+                premisses.len()
+            )));
+        }
+
+        let mut element_mapping = substitution
+            .iter()
+            .map(|(k, v)| (k.clone(), Formula::Ident(Identifier::Element(v.clone()))))
+            .collect::<BTreeMap<_, _>>();
+        // A substitution in one premiss may need a formula of a later one.
+        for _ in 0..2 {
+            for (premiss, schema) in premisses.iter().zip(rule.premises.iter()) {
+                infer_mapping_stmt(premiss, schema, mapping, &mut element_mapping)?;
+            }
+        }
+        substitution.extend(element_substitution(element_mapping)?);
+
+        // The assumptions of the conclusion are those of the premisses without the
+        // discharged ones.
+        let mut lhs = Vec::new();
+        for (premiss, schema) in premisses.iter().zip(rule.premises.iter()) {
+            let discharged = match &schema.lhs {
+                // The discharged assumption can't be read off the premiss.
+                Some(discharged) => Some(get_formula(discharged, mapping).map_err(|_| {
+                    BackendError::BadRequest(format!(
+                        "The discharged assumption {} has to be given in the mapping",
+                        discharged
+                    ))
+                })?),
+                None => None,
+            };
+            for f in premiss.lhs.iter() {
+                let is_discharged = discharged
+                    .as_ref()
+                    .is_some_and(|discharged| alpha_equivalent(f, discharged));
+                if !is_discharged && !contains(&lhs, f) {
+                    lhs.push(f.clone());
+                }
+            }
+        }
+        if premisses.is_empty() {
+            lhs = context
+                .ok_or_else(|| {
+                    BackendError::BadRequest(
+                        "The assumptions are needed for a rule without premisses".to_string(),
+                    )
+                })?
+                .to_vec();
+        }
+
+        let conclusion = Statement {
+            lhs,
+            formula: rule
+                .conclusion
+                .formula
+                .apply_mapping(mapping, substitution)?,
+            sidecondition: premisses
+                .first()
+                .map(|p| p.sidecondition.clone())
+                .unwrap_or_default(),
+        };
+
+        // Applying the rule backwards has to give the premisses again, up to unused
+        // assumptions. This also checks the side conditions.
+        let expected = conclusion.apply_rule(
+            rule.clone(),
+            &mut mapping.clone(),
+            &mut substitution.clone(),
+        )?;
+        for (expected, given) in expected.iter().zip(premisses.iter()) {
+            if !alpha_equivalent(&expected.formula, &given.formula)
+                || !subset(&given.lhs, &expected.lhs)
+            {
+                return Err(BackendError::BadRequest(format!(
+                    "The rule does not derive {} from {}, it expects {}",
+                    conclusion, given, expected
+                )));
+            }
+        }
+        Ok(conclusion)
+    }
+}
@@ -0,0 +1,265 @@
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+use crate::error::{BackendError, BackendResult};
+
+use super::{
+    formula::Formula,
+    sat::{SolverLimits, Verdict},
+    statement::Statement,
+};
+
+// Propositional formulas with `not A` written as `A -> false`. Quantifier free predicates
+// are atoms named by their printed form.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Prop {
+    Atom(String),
+    True,
+    False,
+    And(Box<Prop>, Box<Prop>),
+    Or(Box<Prop>, Box<Prop>),
+    Imp(Box<Prop>, Box<Prop>),
+}
+
+fn imp(lhs: Prop, rhs: Prop) -> Prop {
+    Prop::Imp(Box::new(lhs), Box::new(rhs))
+}
+
+impl Prop {
+    fn of(formula: &Formula) -> Option<Prop> {
+        match formula {
+            Formula::Ident(_) | Formula::Predicate { .. } => Some(Prop::Atom(formula.to_string())),
+            Formula::True => Some(Prop::True),
+            Formula::False => Some(Prop::False),
+            Formula::And { lhs, rhs } => Some(Prop::And(
+                Box::new(Prop::of(lhs)?),
+                Box::new(Prop::of(rhs)?),
+            )),
+            Formula::Or { lhs, rhs } => {
+                Some(Prop::Or(Box::new(Prop::of(lhs)?), Box::new(Prop::of(rhs)?)))
+            }
+            Formula::Imp { lhs, rhs } => Some(imp(Prop::of(lhs)?, Prop::of(rhs)?)),
+            Formula::Not(f) => Some(imp(Prop::of(f)?, Prop::False)),
+            // Quantifiers and equality are beyond the propositional fragment.
+            Formula::Forall { .. } | Formula::Exists { .. } | Formula::Eq { .. } => None,
+        }
+    }
+}
+
+// Dyckhoff's contraction-free sequent calculus G4ip. Every rule makes the sequent
+// smaller in a well-founded order, so the search terminates without loop checking, but
+// the non-invertible rules make it exponential, hence the deadline.
+struct G4ip {
+    deadline: Instant,
+    timed_out: Cell<bool>,
+}
+
+impl G4ip {
+    // A sequent counts as unprovable once the deadline has passed, so `false` is only
+    // reliable if the search did not time out.
+    fn prove(&self, context: &BTreeSet<Prop>, goal: &Prop) -> bool {
+        if Instant::now() >= self.deadline {
+            self.timed_out.set(true);
+            return false;
+        }
+        if context.contains(&Prop::False) || context.contains(goal) {
+            return true;
+        }
+
+        // Invertible right rules.
+        match goal {
+            Prop::True => return true,
+            Prop::And(a, b) => return self.prove(context, a) && self.prove(context, b),
+            Prop::Imp(a, b) => return self.prove(&with(context, &[], &[(**a).clone()]), b),
+            _ => {}
+        }
+
+        // Invertible left rules.
+        for f in context.iter() {
+            let rest = |add: &[Prop]| with(context, &[f], add);
+            match f {
+                Prop::And(a, b) => return self.prove(&rest(&[(**a).clone(), (**b).clone()]), goal),
+                Prop::Or(a, b) => {
+                    return self.prove(&rest(&[(**a).clone()]), goal)
+                        && self.prove(&rest(&[(**b).clone()]), goal)
+                }
+                Prop::Imp(a, b) => match &**a {
+                    Prop::Atom(_) if context.contains(a) => {
+                        return self.prove(&rest(&[(**b).clone()]), goal)
+                    }
+                    Prop::True => return self.prove(&rest(&[(**b).clone()]), goal),
+                    Prop::False => return self.prove(&rest(&[]), goal),
+                    Prop::And(c, d) => {
+                        let curried = imp((**c).clone(), imp((**d).clone(), (**b).clone()));
+                        return self.prove(&rest(&[curried]), goal);
+                    }
+                    Prop::Or(c, d) => {
+                        let split = [
+                            imp((**c).clone(), (**b).clone()),
+                            imp((**d).clone(), (**b).clone()),
+                        ];
+                        return self.prove(&rest(&split), goal);
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        // Non-invertible rules, every alternative has to be tried.
+        if let Prop::Or(a, b) = goal {
+            if self.prove(context, a) || self.prove(context, b) {
+                return true;
+            }
+        }
+        for f in context.iter() {
+            if let Prop::Imp(a, b) = f {
+                if let Prop::Imp(_, d) = &**a {
+                    let rest = with(context, &[f], &[]);
+                    let left = with(&rest, &[], &[imp((**d).clone(), (**b).clone())]);
+                    if self.prove(&left, a) && self.prove(&with(&rest, &[], &[(**b).clone()]), goal)
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+fn with(context: &BTreeSet<Prop>, remove: &[&Prop], add: &[Prop]) -> BTreeSet<Prop> {
+    let mut context = context.clone();
+    for f in remove {
+        context.remove(*f);
+    }
+    context.extend(add.iter().cloned());
+    context
+}
+
+impl Statement {
+    /// Decides intuitionistic validity, `Unknown` if the timeout is exceeded. Returns
+    /// `None` for statements outside of propositional logic, i.e. with quantifiers or
+    /// equality.
+    pub fn check_intuitionistic(&self, limits: &SolverLimits) -> Option<Verdict> {
+        let context = self
+            .lhs
+            .iter()
+            .map(Prop::of)
+            .collect::<Option<BTreeSet<_>>>()?;
+        let goal = Prop::of(&self.formula)?;
+        let prover = G4ip {
+            deadline: Instant::now() + limits.timeout,
+            timed_out: Cell::new(false),
+        };
+        Some(if prover.prove(&context, &goal) {
+            Verdict::Valid
+        } else if prover.timed_out.get() {
+            Verdict::Unknown
+        } else {
+            Verdict::Invalid
+        })
+    }
+
+    /// Runs the intuitionistic check on the blocking thread pool, the prover must not
+    /// stall the async runtime.
+    pub async fn check_intuitionistic_blocking(
+        &self,
+        limits: SolverLimits,
+    ) -> BackendResult<Option<Verdict>> {
+        let statement = self.clone();
+        tokio::task::spawn_blocking(move || statement.check_intuitionistic(&limits))
+            .await
+            .map_err(|e| BackendError::Unknown(format!("intuitionistic check failed: {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::lib::LogicParser;
+
+    fn statement(lhs: &[&str], formula: &str) -> Statement {
+        Statement {
+            lhs: lhs
+                .iter()
+                .map(|f| LogicParser::parse_input(f).unwrap())
+                .collect(),
+            formula: LogicParser::parse_input(formula).unwrap(),
+            sidecondition: vec![],
+        }
+    }
+
+    fn check(lhs: &[&str], formula: &str) -> Option<bool> {
+        match statement(lhs, formula).check_intuitionistic(&SolverLimits::default())? {
+            Verdict::Valid => Some(true),
+            Verdict::Invalid => Some(false),
+            Verdict::Unknown => panic!("{formula} timed out"),
+        }
+    }
+
+    #[test]
+    fn classical_principles_are_not_provable() {
+        for formula in [
+            "A or not A",
+            "not not A -> A",
+            "((A -> B) -> A) -> A",
+            "(not B -> not A) -> A -> B",
+            "(A -> B) or (B -> A)",
+            "not (A and B) -> not A or not B",
+        ] {
+            assert_eq!(check(&[], formula), Some(false), "{formula}");
+        }
+    }
+
+    #[test]
+    fn intuitionistic_tautologies_are_provable() {
+        for formula in [
+            "A -> A",
+            "(A -> B) -> (B -> C) -> A -> C",
+            "(A -> B -> C) -> (A -> B) -> A -> C",
+            "(A and B -> C) -> A -> B -> C",
+            "(A or B -> C) -> (A -> C) and (B -> C)",
+            "A -> not not A",
+            "not not not A -> not A",
+            "not not (A or not A)",
+            "(A -> B) -> not B -> not A",
+            "not (A or B) -> not A and not B",
+            "false -> A",
+            "true",
+        ] {
+            assert_eq!(check(&[], formula), Some(true), "{formula}");
+        }
+    }
+
+    #[test]
+    fn assumptions_are_used() {
+        assert_eq!(check(&["A -> B", "B -> C", "A"], "C"), Some(true));
+        assert_eq!(check(&["A or B", "not A"], "B"), Some(true));
+        assert_eq!(check(&["A -> B"], "B"), Some(false));
+    }
+
+    #[test]
+    fn first_order_statements_are_undecided() {
+        assert_eq!(check(&[], "forall_x p(x) -> p(c)"), None);
+        assert_eq!(check(&["x = y"], "y = x"), None);
+    }
+
+    #[test]
+    fn exceeding_the_timeout_is_unknown() {
+        let limits = SolverLimits {
+            timeout: Duration::ZERO,
+        };
+        assert_eq!(
+            statement(&[], "A or not A").check_intuitionistic(&limits),
+            Some(Verdict::Unknown)
+        );
+        assert_eq!(
+            statement(&[], "forall_x p(x) -> p(c)").check_intuitionistic(&limits),
+            None
+        );
+    }
+}
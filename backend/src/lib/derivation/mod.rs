@@ -1,4 +1,9 @@
 pub mod formula;
+pub mod forward;
+pub mod intuitionistic;
 pub mod sat;
+pub mod search;
+pub mod signature;
 pub mod statement;
 pub mod tree;
+pub mod wellformed;
@@ -1,33 +1,482 @@
-use core::panic;
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::time::Duration;
 
-use log::info;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use z3::{
     ast::{self, Ast, Bool, Int},
-    Config, Context, FuncDecl, SatResult, Solver, Sort,
+    Config, Context, FuncDecl, Model, SatResult, Solver, Sort,
 };
 
 use crate::api::models::SideCondition;
+use crate::error::{BackendError, BackendResult};
 
 use super::{
-    formula::{Formula, Identifier},
+    formula::{Formula, Identifier, Term},
     statement::Statement,
 };
 
+// Predicate extensions are only listed up to this many argument tuples.
+const MAX_TUPLES: usize = 4096;
+// Function values outside of the domain are added to it at most this many times.
+const MAX_CLOSURE_ROUNDS: usize = 8;
+
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+const MAX_TIMEOUT_MS: u64 = 30000;
+const DEFAULT_MEMORY_MB: u32 = 512;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SolverLimits {
+    pub timeout: Duration,
+}
+
+impl Default for SolverLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+        }
+    }
+}
+
+impl SolverLimits {
+    /// Limits requested by a client, capped so a single request cannot occupy the server.
+    pub fn bounded(timeout_ms: Option<u64>) -> Self {
+        Self {
+            timeout: Duration::from_millis(
+                timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).min(MAX_TIMEOUT_MS),
+            ),
+        }
+    }
+
+    fn config(&self) -> Config {
+        let mut cfg = Config::new();
+        cfg.set_timeout_msec(self.timeout.as_millis() as u64);
+        cfg
+    }
+}
+
+/// Caps the memory of Z3 to `Z3_MEMORY_MB`. The limit is process wide, so it is set
+/// once at startup and not per check.
+pub fn set_memory_limit() {
+    let memory_mb = match std::env::var("Z3_MEMORY_MB") {
+        Ok(var) => var.parse::<u32>().unwrap_or_else(|_| {
+            warn!("Z3_MEMORY_MB must be a number of megabytes, using {DEFAULT_MEMORY_MB}");
+            DEFAULT_MEMORY_MB
+        }),
+        Err(_) => DEFAULT_MEMORY_MB,
+    };
+    info!("limiting z3 to {memory_mb} MB");
+    z3::set_global_param("memory_max_size", &memory_mb.to_string());
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub enum Verdict {
+    Valid,
+    Invalid,
+    Unknown,
+}
+
+/// The outcome of a validity check. A failed check carries a counter model if the
+/// solver found one, an undecided check the reason given by the solver, e.g. a timeout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Validity {
+    Valid,
+    Invalid(Option<CounterModel>),
+    Unknown(String),
+}
+
+impl Validity {
+    pub fn verdict(&self) -> Verdict {
+        match self {
+            Validity::Valid => Verdict::Valid,
+            Validity::Invalid(_) => Verdict::Invalid,
+            Validity::Unknown(_) => Verdict::Unknown,
+        }
+    }
+}
+
+// Literals, predicates and elements live in separate namespaces, e.g. `p(p)` is fine,
+// so their Z3 symbols are prefixed with their kind.
+fn symbol(kind: &str, name: &str) -> String {
+    format!("{kind}!{name}")
+}
+
+/// A finite interpretation under which the assumptions hold but the conclusion does not.
+/// The domain contains the elements the model refers to, plus one more when the statement
+/// has quantifiers, and is closed under the functions.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct CounterModel {
+    pub literals: BTreeMap<String, bool>,
+    pub domain: Vec<String>,
+    pub elements: BTreeMap<String, String>,
+    pub predicates: BTreeMap<String, Vec<Vec<String>>>,
+    pub functions: BTreeMap<String, Vec<FunctionValue>>,
+}
+
+/// One entry of a function table in a counter model.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct FunctionValue {
+    pub args: Vec<String>,
+    pub value: String,
+}
+
+fn tuples(domain: &[i64], arity: usize) -> Vec<Vec<i64>> {
+    let mut result = vec![vec![]];
+    for _ in 0..arity {
+        result = result
+            .into_iter()
+            .flat_map(|tuple| {
+                domain.iter().map(move |d| {
+                    let mut tuple = tuple.clone();
+                    tuple.push(*d);
+                    tuple
+                })
+            })
+            .collect();
+    }
+    result
+}
+
+// The argument tuples on the domain for which the predicate holds, `None` if there are too
+// many to list.
+fn extension<'ctx>(
+    ctx: &'ctx Context,
+    model: &Model<'ctx>,
+    func: &FuncDecl<'ctx>,
+    arity: usize,
+    domain: &BTreeSet<i64>,
+) -> Option<BTreeSet<Vec<i64>>> {
+    let values = domain.iter().copied().collect::<Vec<_>>();
+    if values.len().checked_pow(arity as u32).unwrap_or(usize::MAX) > MAX_TUPLES {
+        return None;
+    }
+    let extension = tuples(&values, arity)
+        .into_iter()
+        .filter(|tuple| {
+            let args = tuple
+                .iter()
+                .map(|v| Int::from_i64(ctx, *v))
+                .collect::<Vec<_>>();
+            let args = args.iter().map(|a| a as &dyn Ast).collect::<Vec<_>>();
+            func.apply(&args)
+                .as_bool()
+                .and_then(|b| model.eval(&b, true))
+                .and_then(|b| b.as_bool())
+                .unwrap_or(false)
+        })
+        .collect();
+    Some(extension)
+}
+
+// The values of a function on the domain as raw model values, `None` if there are too
+// many to list. Values outside of the domain are added by the caller.
+fn table<'ctx>(
+    ctx: &'ctx Context,
+    model: &Model<'ctx>,
+    func: &FuncDecl<'ctx>,
+    domain: &BTreeSet<i64>,
+) -> Option<BTreeMap<Vec<i64>, i64>> {
+    let values = domain.iter().copied().collect::<Vec<_>>();
+    let arity = func.arity();
+    if values.len().checked_pow(arity as u32).unwrap_or(usize::MAX) > MAX_TUPLES {
+        return None;
+    }
+    let table = tuples(&values, arity)
+        .into_iter()
+        .filter_map(|tuple| {
+            let args = tuple
+                .iter()
+                .map(|v| Int::from_i64(ctx, *v))
+                .collect::<Vec<_>>();
+            let args = args.iter().map(|a| a as &dyn Ast).collect::<Vec<_>>();
+            let value = func
+                .apply(&args)
+                .as_int()
+                .and_then(|i| model.eval(&i, true))
+                .and_then(|i| i.as_i64())?;
+            Some((tuple, value))
+        })
+        .collect();
+    Some(table)
+}
+
+// A finite structure read off a model. The solver interprets elements as integers, so
+// the structure is only a counter model if the formula is false in it as well.
+#[derive(Debug, Clone, Default)]
+struct Structure {
+    domain: BTreeSet<i64>,
+    // The values of all element constants, including those named like bound elements.
+    elements: BTreeMap<String, i64>,
+    // The elements a literal depends on and the tuples of their values it holds for.
+    literals: BTreeMap<String, (Vec<String>, BTreeSet<Vec<i64>>)>,
+    predicates: BTreeMap<String, BTreeSet<Vec<i64>>>,
+    functions: BTreeMap<String, BTreeMap<Vec<i64>, i64>>,
+}
+
+impl Structure {
+    fn read<'ctx>(
+        ctx: &'ctx Context,
+        model: &Model<'ctx>,
+        quantified: bool,
+        pred_vars: &BTreeMap<String, Int<'ctx>>,
+        bools: &BTreeMap<String, (FuncDecl<'ctx>, Vec<&String>)>,
+        predicates: &BTreeMap<String, FuncDecl<'ctx>>,
+        functions: &BTreeMap<String, FuncDecl<'ctx>>,
+    ) -> Option<Structure> {
+        let elements = pred_vars
+            .iter()
+            .filter_map(|(name, v)| Some((name.clone(), model.eval(v, true)?.as_i64()?)))
+            .collect::<BTreeMap<_, _>>();
+        let mut domain = elements.values().copied().collect::<BTreeSet<_>>();
+        if quantified || (domain.is_empty() && !predicates.is_empty()) {
+            domain.insert(domain.last().map_or(0, |v| v + 1));
+        }
+
+        // Function values may leave the domain, those values are added until it is closed.
+        let mut tables = BTreeMap::new();
+        let mut closed = false;
+        for _ in 0..MAX_CLOSURE_ROUNDS {
+            tables = functions
+                .iter()
+                .map(|(name, func)| Some((name.clone(), table(ctx, model, func, &domain)?)))
+                .collect::<Option<BTreeMap<_, _>>>()?;
+            let outside = tables
+                .values()
+                .flat_map(|table: &BTreeMap<Vec<i64>, i64>| table.values())
+                .filter(|value| !domain.contains(value))
+                .copied()
+                .collect::<Vec<_>>();
+            if outside.is_empty() {
+                closed = true;
+                break;
+            }
+            domain.extend(outside);
+        }
+        if !closed {
+            return None;
+        }
+
+        let literals = bools
+            .iter()
+            .map(|(name, (func, args))| {
+                let tuples = extension(ctx, model, func, args.len(), &domain)?;
+                let args = args.iter().map(|arg| arg.to_string()).collect();
+                Some((name.clone(), (args, tuples)))
+            })
+            .collect::<Option<_>>()?;
+        let predicates = predicates
+            .iter()
+            .map(|(name, func)| {
+                Some((
+                    name.clone(),
+                    extension(ctx, model, func, func.arity(), &domain)?,
+                ))
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Structure {
+            domain,
+            elements,
+            literals,
+            predicates,
+            functions: tables,
+        })
+    }
+
+    fn term(&self, term: &Term, env: &BTreeMap<String, i64>) -> Option<i64> {
+        match term {
+            Term::Element(x) => env.get(x).copied(),
+            Term::Function { name, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.term(arg, env))
+                    .collect::<Option<Vec<_>>>()?;
+                self.functions.get(name)?.get(&args).copied()
+            }
+        }
+    }
+
+    // Whether the formula holds for the values of the elements in `env`, `None` if it
+    // can not be evaluated in the structure.
+    fn holds(&self, formula: &Formula, env: &BTreeMap<String, i64>) -> Option<bool> {
+        let quantified = |x: &String, formula: &Formula| {
+            self.domain
+                .iter()
+                .map(|v| {
+                    let mut env = env.clone();
+                    env.insert(x.clone(), *v);
+                    self.holds(formula, &env)
+                })
+                .collect::<Option<Vec<_>>>()
+        };
+        Some(match formula {
+            Formula::And { lhs, rhs } => self.holds(lhs, env)? & self.holds(rhs, env)?,
+            Formula::Or { lhs, rhs } => self.holds(lhs, env)? | self.holds(rhs, env)?,
+            Formula::Imp { lhs, rhs } => !self.holds(lhs, env)? | self.holds(rhs, env)?,
+            Formula::Not(formula) => !self.holds(formula, env)?,
+            Formula::True => true,
+            Formula::False => false,
+            Formula::Ident(Identifier::Literal(name)) => {
+                let (args, tuples) = self.literals.get(name)?;
+                let values = args
+                    .iter()
+                    .map(|arg| env.get(arg).copied())
+                    .collect::<Option<Vec<_>>>()?;
+                tuples.contains(&values)
+            }
+            Formula::Forall {
+                identifier: Identifier::Element(x),
+                formula,
+            } => quantified(x, formula)?.into_iter().all(|b| b),
+            Formula::Exists {
+                identifier: Identifier::Element(x),
+                formula,
+            } => quantified(x, formula)?.into_iter().any(|b| b),
+            Formula::Predicate {
+                identifier: Identifier::Element(name),
+                identifiers,
+            } => {
+                let values = identifiers
+                    .iter()
+                    .map(|term| self.term(term, env))
+                    .collect::<Option<Vec<_>>>()?;
+                self.predicates.get(name)?.contains(&values)
+            }
+            Formula::Eq { lhs, rhs } => self.term(lhs, env)? == self.term(rhs, env)?,
+            _ => return None,
+        })
+    }
+
+    fn counter_model(&self, formula: &Formula) -> CounterModel {
+        let names = self
+            .domain
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (*v, format!("d{i}")))
+            .collect::<BTreeMap<_, _>>();
+        let tuple = |tuple: &Vec<i64>| tuple.iter().map(|v| names[v].clone()).collect();
+
+        let literals = self
+            .literals
+            .iter()
+            .filter(|(_, (args, _))| args.is_empty())
+            .map(|(name, (_, tuples))| (name.clone(), !tuples.is_empty()))
+            .collect();
+        let mut predicates = self
+            .literals
+            .iter()
+            .filter(|(_, (args, _))| !args.is_empty())
+            .map(|(name, (_, tuples))| (name.clone(), tuples.iter().map(tuple).collect()))
+            .collect::<BTreeMap<_, _>>();
+        predicates.extend(
+            self.predicates
+                .iter()
+                .map(|(name, tuples)| (name.clone(), tuples.iter().map(tuple).collect())),
+        );
+        let functions = self
+            .functions
+            .iter()
+            .map(|(name, table)| {
+                let entries = table
+                    .iter()
+                    .map(|(args, value)| FunctionValue {
+                        args: tuple(args),
+                        value: names[value].clone(),
+                    })
+                    .collect();
+                (name.clone(), entries)
+            })
+            .collect();
+        let elements = formula
+            .free_vars(BTreeSet::new())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|name| {
+                let value = names.get(self.elements.get(&name)?)?.clone();
+                Some((name, value))
+            })
+            .collect();
+
+        CounterModel {
+            literals,
+            domain: names.into_values().collect(),
+            elements,
+            predicates,
+            functions,
+        }
+    }
+}
+
+fn declared<'a, T>(map: &'a BTreeMap<String, T>, kind: &str, name: &str) -> BackendResult<&'a T> {
+    map.get(name)
+        .ok_or_else(|| BackendError::BadRequest(format!("The {kind} {name} is not declared")))
+}
+
+fn check_arity(func: &FuncDecl, kind: &str, name: &str, arity: usize) -> BackendResult<()> {
+    if func.arity() != arity {
+        return Err(BackendError::BadRequest(format!(
+            "The {kind} {name} is used with {} and {arity} arguments",
+            func.arity()
+        )));
+    }
+    Ok(())
+}
+
+impl Term {
+    fn vars(&self, predicate_vars: &mut BTreeSet<String>, functions: &mut BTreeSet<(String, u32)>) {
+        match self {
+            Term::Element(x) => {
+                predicate_vars.insert(x.to_string());
+            }
+            Term::Function { name, args } => {
+                functions.insert((name.to_string(), args.len() as u32));
+                for arg in args {
+                    arg.vars(predicate_vars, functions);
+                }
+            }
+        }
+    }
+
+    fn build_term<'a>(
+        &self,
+        functions: &'a BTreeMap<String, FuncDecl<'a>>,
+        pred_vars: &'a BTreeMap<String, Int<'a>>,
+    ) -> BackendResult<Int<'a>> {
+        match self {
+            Term::Element(x) => Ok(declared(pred_vars, "element", x)?.clone()),
+            Term::Function { name, args } => {
+                let function = declared(functions, "function", name)?;
+                check_arity(function, "function", name, args.len())?;
+                let args = args
+                    .iter()
+                    .map(|arg| arg.build_term(functions, pred_vars))
+                    .collect::<BackendResult<Vec<_>>>()?;
+                let arguments = args.iter().map(|a| a as &dyn Ast).collect::<Vec<_>>();
+                function.apply(&arguments).as_int().ok_or_else(|| {
+                    BackendError::Unknown(format!("The function {name} does not return an element"))
+                })
+            }
+        }
+    }
+}
+
 impl Formula {
     fn vars(
         &self,
         bool_vars: &mut BTreeSet<String>,
         predicate_vars: &mut BTreeSet<String>,
         predicates: &mut BTreeSet<(String, u32)>,
-    ) {
+        functions: &mut BTreeSet<(String, u32)>,
+    ) -> BackendResult<()> {
         match self {
             Formula::And { lhs, rhs } | Formula::Or { lhs, rhs } | Formula::Imp { lhs, rhs } => {
-                lhs.vars(bool_vars, predicate_vars, predicates);
-                rhs.vars(bool_vars, predicate_vars, predicates);
+                lhs.vars(bool_vars, predicate_vars, predicates, functions)?;
+                rhs.vars(bool_vars, predicate_vars, predicates, functions)?;
             }
             Formula::Not(formula) => {
-                formula.vars(bool_vars, predicate_vars, predicates);
+                formula.vars(bool_vars, predicate_vars, predicates, functions)?;
             }
             Formula::True | Formula::False => {}
             Formula::Forall {
@@ -38,28 +487,52 @@ impl Formula {
                 identifier: Identifier::Element(x),
                 formula,
             } => {
-                formula.vars(bool_vars, predicate_vars, predicates);
+                formula.vars(bool_vars, predicate_vars, predicates, functions)?;
                 predicate_vars.insert(x.to_string());
             }
+            Formula::Forall {
+                identifier: Identifier::Literal(x),
+                ..
+            }
+            | Formula::Exists {
+                identifier: Identifier::Literal(x),
+                ..
+            } => {
+                return Err(BackendError::BadRequest(format!(
+                    "Quantifiers must bind an element, not the literal {x}"
+                )))
+            }
             Formula::Predicate {
                 identifier: Identifier::Element(p),
                 identifiers: vec,
             } => {
-                let vars = vec
-                    .iter()
-                    .map(|x| match x {
-                        Identifier::Element(x) => x.to_string(),
-                        _ => panic!("Should never happen"),
-                    })
-                    .collect::<BTreeSet<_>>();
-                predicate_vars.extend(vars);
+                for term in vec {
+                    term.vars(predicate_vars, functions);
+                }
                 predicates.insert((p.to_string(), vec.len() as u32));
             }
+            Formula::Predicate {
+                identifier: Identifier::Literal(p),
+                ..
+            } => {
+                return Err(BackendError::BadRequest(format!(
+                    "The predicate {p} must be named like an element"
+                )))
+            }
+            Formula::Eq { lhs, rhs } => {
+                lhs.vars(predicate_vars, functions);
+                rhs.vars(predicate_vars, functions);
+            }
             Formula::Ident(Identifier::Literal(x)) => {
                 bool_vars.insert(x.to_string());
             }
-            _ => panic!("Should never happen"),
+            Formula::Ident(Identifier::Element(x)) => {
+                return Err(BackendError::BadRequest(format!(
+                    "The element {x} is used as a formula"
+                )))
+            }
         }
+        Ok(())
     }
 
     pub fn build_formula<'a>(
@@ -68,29 +541,29 @@ impl Formula {
         all_vars: &'a BTreeSet<String>,
         bools: &'a BTreeMap<String, (z3::FuncDecl<'_>, Vec<&std::string::String>)>,
         predicates: &'a BTreeMap<String, FuncDecl<'a>>,
+        functions: &'a BTreeMap<String, FuncDecl<'a>>,
         pred_vars: &'a BTreeMap<String, Int<'a>>,
-    ) -> Bool<'a> {
+    ) -> BackendResult<Bool<'a>> {
+        let build =
+            |f: &Formula| f.build_formula(ctx, all_vars, bools, predicates, functions, pred_vars);
         match self {
             Formula::And { lhs, rhs } => {
-                let lhs = lhs.build_formula(ctx, all_vars, bools, predicates, pred_vars);
-                let rhs = rhs.build_formula(ctx, all_vars, bools, predicates, pred_vars);
-                Bool::and(ctx, &[&lhs, &rhs])
+                let lhs = build(lhs)?;
+                let rhs = build(rhs)?;
+                Ok(Bool::and(ctx, &[&lhs, &rhs]))
             }
             Formula::Or { lhs, rhs } => {
-                let lhs = lhs.build_formula(ctx, all_vars, bools, predicates, pred_vars);
-                let rhs = rhs.build_formula(ctx, all_vars, bools, predicates, pred_vars);
-                Bool::or(ctx, &[&lhs, &rhs])
-            }
-            Formula::Not(formula) => {
-                let f = formula.build_formula(ctx, all_vars, bools, predicates, pred_vars);
-                f.not()
+                let lhs = build(lhs)?;
+                let rhs = build(rhs)?;
+                Ok(Bool::or(ctx, &[&lhs, &rhs]))
             }
+            Formula::Not(formula) => Ok(build(formula)?.not()),
             Formula::Ident(Identifier::Literal(name)) => {
-                let (placeholder, args) = bools.get(name).unwrap();
+                let (placeholder, args) = declared(bools, "literal", name)?;
                 let vars = args
                     .iter()
-                    .map(|var| pred_vars.get(*var).unwrap())
-                    .collect::<Vec<_>>();
+                    .map(|var| declared(pred_vars, "element", var))
+                    .collect::<BackendResult<Vec<_>>>()?;
 
                 let mut arguments = Vec::new();
                 for elem in vars.iter() {
@@ -99,69 +572,92 @@ impl Formula {
                 }
 
                 let result = placeholder.apply(&arguments);
-                result.as_bool().unwrap()
+                result.as_bool().ok_or_else(|| {
+                    BackendError::Unknown(format!("The literal {name} is not a boolean"))
+                })
             }
             Formula::Imp { lhs, rhs } => {
-                let lhs = lhs.build_formula(ctx, all_vars, bools, predicates, pred_vars);
-                let rhs = rhs.build_formula(ctx, all_vars, bools, predicates, pred_vars);
-                lhs.implies(&rhs)
+                let lhs = build(lhs)?;
+                let rhs = build(rhs)?;
+                Ok(lhs.implies(&rhs))
             }
-            Formula::True => Bool::from_bool(ctx, true),
-            Formula::False => Bool::from_bool(ctx, false),
+            Formula::True => Ok(Bool::from_bool(ctx, true)),
+            Formula::False => Ok(Bool::from_bool(ctx, false)),
             Formula::Forall {
                 identifier: Identifier::Element(name),
                 formula,
             } => {
-                let name = pred_vars.get(name).unwrap();
-                let f = formula.build_formula(ctx, all_vars, bools, predicates, pred_vars);
-
-                let forall_formula = ast::forall_const(ctx, &[name], &[], &f);
-
-                forall_formula
+                let name = declared(pred_vars, "element", name)?;
+                let f = build(formula)?;
+                Ok(ast::forall_const(ctx, &[name], &[], &f))
             }
             Formula::Exists {
                 identifier: Identifier::Element(name),
                 formula,
             } => {
-                let name = pred_vars.get(name).unwrap();
-                let f = formula.build_formula(ctx, all_vars, bools, predicates, pred_vars);
-
-                let forall_formula = ast::exists_const(ctx, &[name], &[], &f);
-
-                forall_formula
+                let name = declared(pred_vars, "element", name)?;
+                let f = build(formula)?;
+                Ok(ast::exists_const(ctx, &[name], &[], &f))
             }
             Formula::Predicate {
                 identifier: Identifier::Element(name),
                 identifiers: args,
             } => {
-                let predicate = predicates.get(name).unwrap();
+                let predicate = declared(predicates, "predicate", name)?;
+                check_arity(predicate, "predicate", name, args.len())?;
                 let vars = args
                     .iter()
-                    .map(|var| match var {
-                        Identifier::Element(x) => pred_vars.get(x).unwrap(),
-                        _ => panic!("Should never happen"),
-                    })
-                    .collect::<Vec<_>>();
+                    .map(|term| term.build_term(functions, pred_vars))
+                    .collect::<BackendResult<Vec<_>>>()?;
                 let mut arguments = Vec::new();
                 for elem in vars.iter() {
-                    let arg = elem.to_owned() as &dyn Ast;
+                    let arg = elem as &dyn Ast;
                     arguments.push(arg);
                 }
                 let result = predicate.apply(&arguments);
-                result.as_bool().unwrap()
+                result.as_bool().ok_or_else(|| {
+                    BackendError::Unknown(format!("The predicate {name} is not a boolean"))
+                })
+            }
+            Formula::Eq { lhs, rhs } => {
+                let lhs = lhs.build_term(functions, pred_vars)?;
+                let rhs = rhs.build_term(functions, pred_vars)?;
+                Ok(lhs._eq(&rhs))
+            }
+            f => Err(BackendError::BadRequest(format!("{f} can not be encoded"))),
+        }
+    }
+
+    fn has_quantifier(&self) -> bool {
+        match self {
+            Formula::And { lhs, rhs } | Formula::Or { lhs, rhs } | Formula::Imp { lhs, rhs } => {
+                lhs.has_quantifier() || rhs.has_quantifier()
             }
-            _ => panic!("Should never happen"),
+            Formula::Not(formula) => formula.has_quantifier(),
+            Formula::Forall { .. } | Formula::Exists { .. } => true,
+            _ => false,
         }
     }
 
-    pub fn check(&self, sideconditions: &Vec<SideCondition>) -> bool {
+    /// Decides validity within the given limits.
+    pub fn validate(
+        &self,
+        sideconditions: &Vec<SideCondition>,
+        limits: &SolverLimits,
+    ) -> BackendResult<Validity> {
         let mut bool_vars = BTreeSet::new();
         let mut predicate_names = BTreeSet::new();
         let mut predicate_vars = BTreeSet::new();
+        let mut function_names = BTreeSet::new();
 
-        self.vars(&mut bool_vars, &mut predicate_vars, &mut predicate_names);
+        self.vars(
+            &mut bool_vars,
+            &mut predicate_vars,
+            &mut predicate_names,
+            &mut function_names,
+        )?;
 
-        let cfg = Config::new();
+        let cfg = limits.config();
         let ctx = &Context::new(&cfg);
 
         let domain_sort = Sort::int(ctx);
@@ -174,10 +670,19 @@ impl Formula {
                 .into_iter()
                 .map(|_| &domain_sort)
                 .collect::<Vec<_>>();
-            let func = FuncDecl::new(ctx, p.clone(), &args, &bool_sort);
+            let func = FuncDecl::new(ctx, symbol("pred", &p), &args, &bool_sort);
             predicates.insert(p, func);
         }
 
+        // Function symbols are uninterpreted functions on the domain.
+        let mut functions = BTreeMap::new();
+
+        for (f, arg_num) in function_names {
+            let args = (0..arg_num).map(|_| &domain_sort).collect::<Vec<_>>();
+            let func = FuncDecl::new(ctx, symbol("fun", &f), &args, &domain_sort);
+            functions.insert(f, func);
+        }
+
         let mut bools = BTreeMap::new();
 
         for v in bool_vars {
@@ -207,23 +712,68 @@ impl Formula {
                 .into_iter()
                 .map(|_| &domain_sort)
                 .collect::<Vec<_>>();
-            let func = FuncDecl::new(ctx, v.clone(), &args, &bool_sort);
+            let func = FuncDecl::new(ctx, symbol("lit", &v), &args, &bool_sort);
             bools.insert(v, (func, args_vars));
         }
 
         let mut pred_vars = BTreeMap::new();
         for name in predicate_vars.iter() {
-            let variable = ast::Int::new_const(ctx, name.clone());
+            let variable = ast::Int::new_const(ctx, symbol("elem", name));
             pred_vars.insert(name.clone(), variable);
         }
 
-        let formula = self.build_formula(ctx, &predicate_vars, &bools, &predicates, &pred_vars);
+        let formula = self.build_formula(
+            ctx,
+            &predicate_vars,
+            &bools,
+            &predicates,
+            &functions,
+            &pred_vars,
+        )?;
 
         let solver = Solver::new(ctx);
         solver.assert(&formula.not());
-        let result = solver.check();
 
-        result == SatResult::Unsat
+        Ok(match solver.check() {
+            SatResult::Unsat => Validity::Valid,
+            SatResult::Unknown => Validity::Unknown(
+                solver
+                    .get_reason_unknown()
+                    .unwrap_or_else(|| "unknown".to_string()),
+            ),
+            SatResult::Sat => {
+                let Some(model) = solver.get_model() else {
+                    return Ok(Validity::Invalid(None));
+                };
+
+                let structure = Structure::read(
+                    ctx,
+                    &model,
+                    self.has_quantifier(),
+                    &pred_vars,
+                    &bools,
+                    &predicates,
+                    &functions,
+                );
+                match structure {
+                    Some(structure)
+                        if structure.holds(self, &structure.elements) == Some(false) =>
+                    {
+                        Validity::Invalid(Some(structure.counter_model(self)))
+                    }
+                    // The model could not be read off as a finite counter model.
+                    _ => Validity::Invalid(None),
+                }
+            }
+        })
+    }
+
+    pub fn check(
+        &self,
+        sideconditions: &Vec<SideCondition>,
+        limits: &SolverLimits,
+    ) -> BackendResult<Verdict> {
+        Ok(self.validate(sideconditions, limits)?.verdict())
     }
 }
 
@@ -250,8 +800,93 @@ impl Statement {
         }
     }
 
-    pub fn check(&self) -> bool {
+    pub fn check(&self, limits: &SolverLimits) -> BackendResult<Verdict> {
         let formula = self.build_implication();
-        formula.check(&self.sidecondition)
+        formula.check(&self.sidecondition, limits)
+    }
+
+    pub fn validate(&self, limits: &SolverLimits) -> BackendResult<Validity> {
+        let formula = self.build_implication();
+        formula.validate(&self.sidecondition, limits)
+    }
+
+    /// Runs the validity check on the blocking thread pool, the solver must not stall
+    /// the async runtime.
+    pub async fn validate_blocking(&self, limits: SolverLimits) -> BackendResult<Validity> {
+        let statement = self.clone();
+        tokio::task::spawn_blocking(move || statement.validate(&limits))
+            .await
+            .map_err(|e| BackendError::Unknown(format!("validity check failed: {e}")))?
+    }
+}
+
+impl Display for CounterModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = self
+            .literals
+            .iter()
+            .map(|(name, value)| format!("{name} = {value}"))
+            .collect::<Vec<_>>();
+        if !self.domain.is_empty() {
+            parts.push(format!("domain = {{{}}}", self.domain.join(", ")));
+        }
+        parts.extend(
+            self.elements
+                .iter()
+                .map(|(name, value)| format!("{name} = {value}")),
+        );
+        parts.extend(self.predicates.iter().map(|(name, tuples)| {
+            let tuples = tuples
+                .iter()
+                .map(|t| format!("({})", t.join(", ")))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{name} = {{{tuples}}}")
+        }));
+        parts.extend(self.functions.iter().map(|(name, entries)| {
+            let entries = entries
+                .iter()
+                .map(|e| format!("({}) -> {}", e.args.join(", "), e.value))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{name} = {{{entries}}}")
+        }));
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::LogicParser;
+
+    fn structure(domain: &[i64], p: &[i64]) -> Structure {
+        Structure {
+            domain: domain.iter().copied().collect(),
+            predicates: BTreeMap::from([("p".to_string(), p.iter().map(|v| vec![*v]).collect())]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn counter_models_are_checked_on_their_domain() {
+        let formula = LogicParser::parse_input("(forall_x p(x)) or (forall_x not p(x))").unwrap();
+        let env = BTreeMap::new();
+        // With a single element one of the disjuncts holds.
+        assert_eq!(structure(&[0], &[0]).holds(&formula, &env), Some(true));
+        assert_eq!(structure(&[0], &[]).holds(&formula, &env), Some(true));
+
+        let model = structure(&[0, 1], &[0]);
+        assert_eq!(model.holds(&formula, &env), Some(false));
+        let model = model.counter_model(&formula);
+        assert_eq!(model.domain, vec!["d0", "d1"]);
+        assert_eq!(model.predicates["p"], vec![vec!["d0".to_string()]]);
+    }
+
+    #[test]
+    fn unknown_symbols_are_not_evaluated() {
+        let formula = LogicParser::parse_input("q(x) or A").unwrap();
+        let env = BTreeMap::from([("x".to_string(), 0)]);
+        assert_eq!(structure(&[0], &[0]).holds(&formula, &env), None);
     }
 }
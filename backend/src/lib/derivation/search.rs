@@ -0,0 +1,564 @@
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::{
+    api::models::{CreateTreeRequest, Node},
+    error::{BackendError, BackendResult},
+    lib::rule::{DerivationRule, RuleFormula, RuleIdentifier, Rules},
+};
+
+use super::{
+    formula::{Formula, Identifier, Term},
+    statement::Statement,
+};
+
+const DEFAULT_DEPTH: u32 = 10;
+const DEFAULT_TIMEOUT_MS: u64 = 1000;
+const MAX_DEPTH: u32 = 20;
+const MAX_TIMEOUT_MS: u64 = 10000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchLimits {
+    pub max_depth: u32,
+    pub timeout: Duration,
+    /// Whether the classical rules may be used.
+    pub classical: bool,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_DEPTH,
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            classical: false,
+        }
+    }
+}
+
+impl SearchLimits {
+    /// Limits requested by a client, capped so a single request cannot occupy the server.
+    pub fn bounded(max_depth: Option<u32>, timeout_ms: Option<u64>) -> Self {
+        Self {
+            max_depth: max_depth.unwrap_or(DEFAULT_DEPTH).min(MAX_DEPTH),
+            timeout: Duration::from_millis(
+                timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).min(MAX_TIMEOUT_MS),
+            ),
+            classical: false,
+        }
+    }
+
+    pub fn with_classical(self, classical: bool) -> Self {
+        Self { classical, ..self }
+    }
+}
+
+type Mapping = (
+    BTreeMap<RuleIdentifier, Formula>,
+    BTreeMap<RuleIdentifier, String>,
+);
+
+struct Derivation {
+    statement: Statement,
+    rule: Rules,
+    premisses: Vec<Derivation>,
+}
+
+impl Derivation {
+    fn into_nodes(self, nodes: &mut Vec<Node>) -> Uuid {
+        let premisses = self
+            .premisses
+            .into_iter()
+            .map(|premiss| premiss.into_nodes(nodes))
+            .collect();
+        let name = Uuid::new_v4();
+        nodes.push(Node {
+            name,
+            statement: self.statement,
+            rule: self.rule,
+            premisses,
+        });
+        name
+    }
+}
+
+// Formulas that may serve as the major premise of an elimination rule and the
+// element names that can be chosen for unbound rule variables.
+struct Candidates {
+    formulas: BTreeSet<Formula>,
+    elements: BTreeSet<String>,
+}
+
+impl Candidates {
+    fn of(goal: &Statement) -> Self {
+        let mut formulas = BTreeSet::new();
+        let mut elements = BTreeSet::new();
+        for f in goal.lhs.iter() {
+            subformulas(f, &mut formulas);
+            element_names(f, &mut elements);
+        }
+        element_names(&goal.formula, &mut elements);
+        Self { formulas, elements }
+    }
+}
+
+fn subformulas(formula: &Formula, acc: &mut BTreeSet<Formula>) {
+    acc.insert(formula.clone());
+    match formula {
+        Formula::And { lhs, rhs } | Formula::Or { lhs, rhs } | Formula::Imp { lhs, rhs } => {
+            subformulas(lhs, acc);
+            subformulas(rhs, acc);
+        }
+        Formula::Not(f) => subformulas(f, acc),
+        _ => {}
+    }
+}
+
+fn element_names(formula: &Formula, acc: &mut BTreeSet<String>) {
+    match formula {
+        Formula::And { lhs, rhs } | Formula::Or { lhs, rhs } | Formula::Imp { lhs, rhs } => {
+            element_names(lhs, acc);
+            element_names(rhs, acc);
+        }
+        Formula::Not(f) => element_names(f, acc),
+        Formula::Ident(Identifier::Element(s)) => {
+            acc.insert(s.clone());
+        }
+        Formula::Forall {
+            identifier,
+            formula,
+        }
+        | Formula::Exists {
+            identifier,
+            formula,
+        } => {
+            if let Identifier::Element(s) = identifier {
+                acc.insert(s.clone());
+            }
+            element_names(formula, acc);
+        }
+        Formula::Predicate { identifiers, .. } => {
+            acc.extend(identifiers.iter().flat_map(|t| t.elements()));
+        }
+        Formula::Eq { lhs, rhs } => {
+            acc.extend(lhs.elements());
+            acc.extend(rhs.elements());
+        }
+        Formula::Ident(Identifier::Literal(_)) | Formula::True | Formula::False => {}
+    }
+}
+
+fn bind(
+    mapping: &mut BTreeMap<RuleIdentifier, Formula>,
+    identifier: &RuleIdentifier,
+    formula: &Formula,
+) -> bool {
+    if let RuleIdentifier::Negation(_) = identifier {
+        return match formula {
+            Formula::Not(f) => bind(mapping, &identifier.base(), f),
+            _ => false,
+        };
+    }
+    match mapping.get(identifier) {
+        Some(bound) => bound == formula,
+        None => {
+            mapping.insert(identifier.clone(), formula.clone());
+            true
+        }
+    }
+}
+
+fn bind_element(
+    substitution: &mut BTreeMap<RuleIdentifier, String>,
+    identifier: &RuleIdentifier,
+    element: &String,
+) -> bool {
+    match substitution.get(identifier) {
+        Some(bound) => bound == element,
+        None => {
+            substitution.insert(identifier.clone(), element.clone());
+            true
+        }
+    }
+}
+
+// Matches a rule formula against a concrete formula, extending the mappings.
+// Substitutions are left to `Statement::apply_rule`, which checks them anyway.
+fn unify(
+    rule: &RuleFormula,
+    formula: &Formula,
+    mapping: &mut BTreeMap<RuleIdentifier, Formula>,
+    substitution: &mut BTreeMap<RuleIdentifier, String>,
+) -> bool {
+    match (rule, formula) {
+        (RuleFormula::Ident(i), f) => bind(mapping, i, f),
+        (RuleFormula::And { lhs: rl, rhs: rr }, Formula::And { lhs, rhs })
+        | (RuleFormula::Or { lhs: rl, rhs: rr }, Formula::Or { lhs, rhs })
+        | (RuleFormula::Imp { lhs: rl, rhs: rr }, Formula::Imp { lhs, rhs }) => {
+            bind(mapping, rl, lhs) && bind(mapping, rr, rhs)
+        }
+        (RuleFormula::Not(i), Formula::Not(f)) => bind(mapping, i, f),
+        (RuleFormula::True, Formula::True) | (RuleFormula::False, Formula::False) => true,
+        (
+            RuleFormula::Forall {
+                identifier: ri,
+                formula: rf,
+            },
+            Formula::Forall {
+                identifier: Identifier::Element(name),
+                formula: f,
+            },
+        )
+        | (
+            RuleFormula::Exists {
+                identifier: ri,
+                formula: rf,
+            },
+            Formula::Exists {
+                identifier: Identifier::Element(name),
+                formula: f,
+            },
+        ) => bind_element(substitution, ri, name) && unify(rf, f, mapping, substitution),
+        (
+            RuleFormula::Eq { lhs: rl, rhs: rr },
+            Formula::Eq {
+                lhs: Term::Element(lhs),
+                rhs: Term::Element(rhs),
+            },
+        ) => bind_element(substitution, rl, lhs) && bind_element(substitution, rr, rhs),
+        (RuleFormula::Substitution { .. }, _) => true,
+        _ => false,
+    }
+}
+
+fn is_bound(identifier: &RuleIdentifier, mapping: &Mapping) -> bool {
+    match identifier {
+        RuleIdentifier::Formula(_) | RuleIdentifier::Negation(_) => {
+            mapping.0.contains_key(&identifier.base())
+        }
+        RuleIdentifier::Element(_) => mapping.1.contains_key(identifier),
+    }
+}
+
+fn candidate_mappings(
+    goal: &Statement,
+    rule: &DerivationRule,
+    candidates: &Candidates,
+) -> Vec<Mapping> {
+    let mut mapping = BTreeMap::new();
+    let mut substitution = BTreeMap::new();
+    match &rule.conclusion.formula {
+        // `apply_rule` expects the instantiated formula for substitution conclusions.
+        RuleFormula::Substitution { identifier, .. } => {
+            mapping.insert(identifier.clone(), goal.formula.clone());
+        }
+        conclusion => {
+            if !unify(conclusion, &goal.formula, &mut mapping, &mut substitution) {
+                return vec![];
+            }
+        }
+    }
+
+    // Structured premises with unbound identifiers are matched against the subformulas
+    // of the assumptions, like the major premise of an elimination rule.
+    let mut partial = vec![(mapping, substitution)];
+    for premise in rule.premises.iter() {
+        if matches!(
+            premise.formula,
+            RuleFormula::Ident(_) | RuleFormula::Substitution { .. }
+        ) {
+            continue;
+        }
+        let mut next = Vec::new();
+        for current in partial {
+            if premise
+                .formula
+                .identifiers()
+                .iter()
+                .all(|i| is_bound(i, &current))
+            {
+                next.push(current);
+                continue;
+            }
+            let mut unified = Vec::new();
+            for f in candidates.formulas.iter() {
+                let (mut mapping, mut substitution) = current.clone();
+                if unify(&premise.formula, f, &mut mapping, &mut substitution) {
+                    unified.push((mapping, substitution));
+                }
+            }
+            if unified.is_empty() {
+                unified.push(current);
+            }
+            next.extend(unified);
+        }
+        partial = next;
+    }
+
+    // Remaining element identifiers are enumerated, unbound formulas can not be guessed.
+    let mut result = Vec::new();
+    for current in partial {
+        let unbound = rule
+            .identifiers()
+            .into_iter()
+            .filter(|i| !is_bound(i, &current))
+            .collect::<Vec<_>>();
+        if unbound
+            .iter()
+            .any(|i| matches!(i, RuleIdentifier::Formula(_)))
+        {
+            continue;
+        }
+        let (mapping, substitution) = current;
+        let mut substitutions = vec![substitution];
+        for identifier in unbound {
+            let mut extended = Vec::new();
+            for substitution in substitutions {
+                for element in candidates.elements.iter() {
+                    let mut substitution = substitution.clone();
+                    substitution.insert(identifier.clone(), element.clone());
+                    extended.push(substitution);
+                }
+            }
+            substitutions = extended;
+        }
+        result.extend(substitutions.into_iter().map(|s| (mapping.clone(), s)));
+    }
+    result
+}
+
+fn same_sequent(a: &Statement, b: &Statement) -> bool {
+    a.formula == b.formula
+        && a.sidecondition == b.sidecondition
+        && a.lhs.iter().collect::<BTreeSet<_>>() == b.lhs.iter().collect::<BTreeSet<_>>()
+}
+
+struct ProofSearch {
+    rules: Vec<DerivationRule>,
+    deadline: Instant,
+    timed_out: Cell<bool>,
+}
+
+impl ProofSearch {
+    fn new(limits: &SearchLimits) -> Self {
+        // Alpha renaming only changes bound names and would double the branching factor.
+        let rules = Rules::all_rules()
+            .into_iter()
+            .filter(|rule| !matches!(rule.name, Rules::AlphaExists | Rules::AlphaForall))
+            .filter(|rule| limits.classical || !rule.name.is_classical())
+            .collect();
+        Self {
+            rules,
+            deadline: Instant::now() + limits.timeout,
+            timed_out: Cell::new(false),
+        }
+    }
+
+    fn prove(
+        &self,
+        goal: &Statement,
+        depth: u32,
+        branch: &mut Vec<Statement>,
+    ) -> Option<Derivation> {
+        if Instant::now() >= self.deadline {
+            self.timed_out.set(true);
+            return None;
+        }
+        if depth == 0 || branch.iter().any(|s| same_sequent(s, goal)) {
+            return None;
+        }
+
+        branch.push(goal.clone());
+        let result = self.prove_with_rules(goal, depth, branch);
+        branch.pop();
+        result
+    }
+
+    fn prove_with_rules(
+        &self,
+        goal: &Statement,
+        depth: u32,
+        branch: &mut Vec<Statement>,
+    ) -> Option<Derivation> {
+        let candidates = Candidates::of(goal);
+        for rule in self.rules.iter() {
+            for (mut mapping, mut substitution) in candidate_mappings(goal, rule, &candidates) {
+                let Ok(premisses) = goal.apply_rule(rule.clone(), &mut mapping, &mut substitution)
+                else {
+                    continue;
+                };
+
+                let mut derived = Vec::new();
+                for premiss in premisses.iter() {
+                    match self.prove(premiss, depth - 1, branch) {
+                        Some(derivation) => derived.push(derivation),
+                        None => break,
+                    }
+                }
+                if derived.len() == premisses.len() {
+                    return Some(Derivation {
+                        statement: goal.clone(),
+                        rule: rule.name.clone(),
+                        premisses: derived,
+                    });
+                }
+                if self.timed_out.get() {
+                    return None;
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Statement {
+    /// Searches backwards for a derivation using iterative deepening, so the first tree
+    /// found is one of minimal height. Returns `None` if the limits are exhausted.
+    pub fn search_proof(&self, limits: SearchLimits) -> Option<CreateTreeRequest> {
+        let search = ProofSearch::new(&limits);
+        for depth in 1..=limits.max_depth {
+            if let Some(derivation) = search.prove(self, depth, &mut Vec::new()) {
+                let mut nodes = Vec::new();
+                let root_id = derivation.into_nodes(&mut nodes);
+                return Some(CreateTreeRequest { nodes, root_id });
+            }
+            if search.timed_out.get() {
+                break;
+            }
+        }
+        None
+    }
+
+    /// Runs the proof search on the blocking thread pool, the search must not stall the
+    /// async runtime.
+    pub async fn search_proof_blocking(
+        &self,
+        limits: SearchLimits,
+    ) -> BackendResult<Option<CreateTreeRequest>> {
+        let statement = self.clone();
+        tokio::task::spawn_blocking(move || statement.search_proof(limits))
+            .await
+            .map_err(|e| BackendError::Unknown(format!("proof search failed: {e}")))
+    }
+
+    /// Runs `complete_step` for each of the steps on the blocking thread pool.
+    pub async fn complete_steps_blocking(
+        &self,
+        steps: Vec<(Rules, Vec<Statement>)>,
+        limits: SearchLimits,
+    ) -> BackendResult<Vec<Option<CreateTreeRequest>>> {
+        let statement = self.clone();
+        tokio::task::spawn_blocking(move || {
+            steps
+                .iter()
+                .map(|(rule, premisses)| statement.complete_step(rule, premisses, limits))
+                .collect()
+        })
+        .await
+        .map_err(|e| BackendError::Unknown(format!("proof search failed: {e}")))
+    }
+
+    /// Searches derivations of the premisses of a rule applied to this statement and
+    /// joins them into one tree.
+    pub fn complete_step(
+        &self,
+        rule: &Rules,
+        premisses: &[Statement],
+        limits: SearchLimits,
+    ) -> Option<CreateTreeRequest> {
+        let mut nodes = Vec::new();
+        let mut roots = Vec::new();
+        for premiss in premisses {
+            let tree = premiss.search_proof(limits)?;
+            roots.push(tree.root_id);
+            nodes.extend(tree.nodes);
+        }
+        let root_id = Uuid::new_v4();
+        nodes.push(Node {
+            name: root_id,
+            statement: self.clone(),
+            rule: rule.clone(),
+            premisses: roots,
+        });
+        Some(CreateTreeRequest { nodes, root_id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::derivation::tree::check_tree;
+    use crate::lib::LogicParser;
+
+    fn statement(lhs: &[&str], formula: &str) -> Statement {
+        Statement {
+            lhs: lhs
+                .iter()
+                .map(|f| LogicParser::parse_input(f).unwrap())
+                .collect(),
+            formula: LogicParser::parse_input(formula).unwrap(),
+            sidecondition: vec![],
+        }
+    }
+
+    fn assert_derives(goal: &Statement, tree: &CreateTreeRequest) {
+        let root = tree.nodes.iter().find(|n| n.name == tree.root_id).unwrap();
+        assert_eq!(root.statement, *goal);
+        if let Err(err) = check_tree(tree.root_id, &tree.nodes) {
+            panic!("{goal}: {err}");
+        }
+    }
+
+    #[test]
+    fn found_derivations_pass_the_checker() {
+        for (lhs, formula) in [
+            (&[][..], "A -> A"),
+            (&[][..], "A and B -> B and A"),
+            (&[][..], "A or B -> B or A"),
+            (&[][..], "(A -> B) -> (B -> C) -> A -> C"),
+            (&[][..], "A -> not not A"),
+            (&["A", "not A"][..], "B"),
+            (&["forall_x p(x)"][..], "p(c)"),
+            (&["p(c)"][..], "exists_x p(x)"),
+            (&[][..], "forall_x (x = x)"),
+            (&["x = y", "p(x)"][..], "p(y)"),
+        ] {
+            let goal = statement(lhs, formula);
+            let tree = goal
+                .search_proof(SearchLimits::default())
+                .unwrap_or_else(|| panic!("no derivation of {goal}"));
+            assert_derives(&goal, &tree);
+        }
+    }
+
+    #[test]
+    fn classical_rules_only_when_allowed() {
+        let goal = statement(&[], "A or not A");
+        let limits = SearchLimits::bounded(Some(6), None);
+        assert!(goal.search_proof(limits).is_none());
+
+        let tree = goal.search_proof(limits.with_classical(true)).unwrap();
+        assert_derives(&goal, &tree);
+        assert!(tree.nodes.iter().any(|n| n.rule.is_classical()));
+    }
+
+    #[test]
+    fn invalid_statements_have_no_derivation() {
+        let limits = SearchLimits::bounded(Some(6), None).with_classical(true);
+        for formula in ["A -> B", "A or B", "exists_x p(x) -> forall_x p(x)"] {
+            assert!(statement(&[], formula).search_proof(limits).is_none());
+        }
+    }
+
+    #[test]
+    fn completed_steps_pass_the_checker() {
+        let goal = statement(&[], "A and B -> B and A");
+        let premiss = statement(&["A and B"], "B and A");
+        let tree = goal
+            .complete_step(&Rules::ImplIntro, &[premiss], SearchLimits::default())
+            .unwrap();
+        assert_derives(&goal, &tree);
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    api::models::SideCondition,
+    error::{BackendError, BackendResult},
+};
+
+use super::{
+    formula::{Formula, Identifier, Term},
+    statement::Statement,
+};
+
+/// The names used in a formula or statement, with the arity of every predicate and
+/// function symbol.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct Signature {
+    pub literals: BTreeSet<String>,
+    pub elements: BTreeSet<String>,
+    pub predicates: BTreeMap<String, usize>,
+    pub functions: BTreeMap<String, usize>,
+}
+
+impl Signature {
+    fn symbol(&mut self, name: &str, arity: usize, predicate: bool) -> BackendResult<()> {
+        let (kind, symbols, other) = if predicate {
+            ("predicate", &mut self.predicates, &self.functions)
+        } else {
+            ("function", &mut self.functions, &self.predicates)
+        };
+        if other.contains_key(name) {
+            return Err(BackendError::BadRequest(format!(
+                "{name} is used both as a predicate and as a function"
+            )));
+        }
+        match symbols.insert(name.to_string(), arity) {
+            Some(known) if known != arity => Err(BackendError::BadRequest(format!(
+                "The {kind} {name} is used with {known} and with {arity} arguments"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn identifier(&mut self, identifier: &Identifier) {
+        match identifier {
+            Identifier::Literal(name) => self.literals.insert(name.clone()),
+            Identifier::Element(name) => self.elements.insert(name.clone()),
+        };
+    }
+
+    fn term(&mut self, term: &Term) -> BackendResult<()> {
+        match term {
+            Term::Element(name) => {
+                self.elements.insert(name.clone());
+            }
+            Term::Function { name, args } => {
+                self.symbol(name, args.len(), false)?;
+                for arg in args {
+                    self.term(arg)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn formula(&mut self, formula: &Formula) -> BackendResult<()> {
+        match formula {
+            Formula::And { lhs, rhs } | Formula::Or { lhs, rhs } | Formula::Imp { lhs, rhs } => {
+                self.formula(lhs)?;
+                self.formula(rhs)?;
+            }
+            Formula::Not(formula) => self.formula(formula)?,
+            Formula::True | Formula::False => {}
+            Formula::Ident(identifier) => self.identifier(identifier),
+            Formula::Forall {
+                identifier,
+                formula,
+            }
+            | Formula::Exists {
+                identifier,
+                formula,
+            } => {
+                self.identifier(identifier);
+                self.formula(formula)?;
+            }
+            Formula::Predicate {
+                identifier,
+                identifiers,
+            } => {
+                let name = match identifier {
+                    Identifier::Element(name) | Identifier::Literal(name) => name,
+                };
+                self.symbol(name, identifiers.len(), true)?;
+                for term in identifiers {
+                    self.term(term)?;
+                }
+            }
+            Formula::Eq { lhs, rhs } => {
+                self.term(lhs)?;
+                self.term(rhs)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Formula {
+    /// Infers the signature, rejecting symbols used with different arities.
+    pub fn signature(&self) -> BackendResult<Signature> {
+        let mut signature = Signature::default();
+        signature.formula(self)?;
+        Ok(signature)
+    }
+}
+
+impl Statement {
+    /// Infers the signature over all formulas of the statement, a predicate has to be
+    /// used with the same arity in the assumptions and the conclusion.
+    pub fn signature(&self) -> BackendResult<Signature> {
+        let mut signature = Signature::default();
+        for f in self.lhs.iter() {
+            signature.formula(f)?;
+        }
+        signature.formula(&self.formula)?;
+        for condition in self.sidecondition.iter() {
+            match condition {
+                SideCondition::NotFree(pair) => {
+                    signature.identifier(&pair.element);
+                    signature.identifier(&pair.placeholder);
+                }
+            }
+        }
+        Ok(signature)
+    }
+}
@@ -10,7 +10,7 @@ use crate::{
     lib::rule::{apply::get_formula, DerivationRule, RuleFormula, RuleIdentifier, Rules},
 };
 
-use super::formula::{Formula, Identifier};
+use super::formula::{alpha_equivalent, Formula, Identifier};
 
 #[derive(
     Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams, Ord, PartialEq, PartialOrd, Eq,
@@ -63,7 +63,13 @@ impl Statement {
         substitution: &BTreeMap<RuleIdentifier, String>,
     ) -> BackendResult<()> {
         // First check if the rule is applicable
-        let res = match (&self.formula, &rule.conclusion.formula) {
+        self.matches_conclusion(rule)?;
+        // Check sideconditions
+        self.check_side_conditions(rule, substitution)
+    }
+
+    pub fn matches_conclusion(&self, rule: &DerivationRule) -> BackendResult<()> {
+        match (&self.formula, &rule.conclusion.formula) {
             (_, RuleFormula::Ident(_)) => Ok(()),
             (Formula::And { .. }, RuleFormula::And { .. }) => Ok(()),
             (Formula::Or { .. }, RuleFormula::Or { .. }) => Ok(()),
@@ -74,21 +80,25 @@ impl Statement {
             (Formula::Forall { .. }, RuleFormula::Forall { .. }) => Ok(()),
             (Formula::Exists { .. }, RuleFormula::Exists { .. }) => Ok(()),
             (Formula::Predicate { .. }, RuleFormula::Ident(_)) => Ok(()),
+            (Formula::Eq { .. }, RuleFormula::Eq { .. }) => Ok(()),
             (_, RuleFormula::Substitution { .. }) => Ok(()),
             _ => Err(BackendError::BadRequest(
                 "The rule is not applicable".to_string(),
             )),
-        };
-        res?;
-
-        // Check sideconditions
+        }
+    }
 
+    pub fn check_side_conditions(
+        &self,
+        rule: &DerivationRule,
+        substitution: &BTreeMap<RuleIdentifier, String>,
+    ) -> BackendResult<()> {
         match rule.name {
             Rules::Ax => {
                 // The lhs must include the rhs.
                 let rhs = &self.formula;
                 let lhs = &self.lhs;
-                if !lhs.contains(rhs) {
+                if !lhs.iter().any(|f| alpha_equivalent(f, rhs)) {
                     return Err(BackendError::BadRequest(
                         "The rhs must be in the lhs".to_string(),
                     ));
@@ -202,7 +212,7 @@ impl Statement {
                 "Could not find the substitution".to_string(),
             ))?;
             // println!("Substitution {:?} -> {:?}", from, to);
-            let new_formula = formula.apply_substitution(to, from, BTreeSet::new())?;
+            let new_formula = formula.apply_substitution(to, from)?;
             // println!("Substitution reversed {:?}", new_formula);
             mapping.insert(identifier.clone(), new_formula);
         }
@@ -212,12 +222,23 @@ impl Statement {
             .formula
             .apply_mapping(mapping, substitution)?;
 
-        if self.formula != conclusion {
+        if !alpha_equivalent(&self.formula, &conclusion) {
             return Err(BackendError::BadRequest(
                 "The conclusion of the rule does not match the target formula".to_string(),
             ));
         }
 
+        self.instantiate_premisses(&rule, mapping, substitution)
+    }
+
+    /// The premisses of the rule under the mapping, with the assumptions of this
+    /// statement and the assumption the premise discharges.
+    pub fn instantiate_premisses(
+        &self,
+        rule: &DerivationRule,
+        mapping: &BTreeMap<RuleIdentifier, Formula>,
+        substitution: &BTreeMap<RuleIdentifier, String>,
+    ) -> BackendResult<Vec<Statement>> {
         let res = rule
             .premises
             .iter()
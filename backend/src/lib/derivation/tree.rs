@@ -1,26 +1,44 @@
 use std::collections::{BTreeMap, BTreeSet};
 
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::{
     api::models::Node,
     error::{BackendError, BackendResult},
+    lib::session::Goal,
     lib::{
-        derivation::formula::{self, Identifier},
-        rule::{DerivationRule, RuleFormula, RuleIdentifier, RuleStatement, Rules},
+        derivation::formula::{alpha_equivalent, fresh_name, Identifier, Term},
+        rule::{
+            apply::get_formula, DerivationRule, RuleFormula, RuleIdentifier, RuleStatement, Rules,
+        },
     },
 };
 
-use super::{formula::Formula, statement::Statement};
+use super::{
+    formula::Formula,
+    forward::{contains, subset},
+    statement::Statement,
+};
 
 fn add_mapping(
     mapping: &mut BTreeMap<RuleIdentifier, Formula>,
     rule: &RuleIdentifier,
     formula: &Formula,
 ) -> BackendResult<()> {
+    if let RuleIdentifier::Negation(_) = rule {
+        return match formula {
+            Formula::Not(f) => add_mapping(mapping, &rule.base(), f),
+            _ => Err(BackendError::BadRequest(format!(
+                "Could not infer mapping. {} is not a negation.",
+                formula
+            ))),
+        };
+    }
     if let Some(formula_in_mapping) = mapping.get(&rule) {
-        if formula_in_mapping != formula {
-            return Err(BackendError::Unknown(format!("Mapping already exists for rule: {:?} with formula: {:?} but tried to add formula: {:?}", rule, formula_in_mapping, formula)));
+        if !alpha_equivalent(formula_in_mapping, formula) {
+            return Err(BackendError::BadRequest(format!("Mapping already exists for rule: {:?} with formula: {:?} but tried to add formula: {:?}", rule, formula_in_mapping, formula)));
         } else {
             return Ok(());
         }
@@ -30,6 +48,79 @@ fn add_mapping(
     }
 }
 
+fn add_element_mapping(
+    element_mapping: &mut BTreeMap<RuleIdentifier, Formula>,
+    rule: &RuleIdentifier,
+    element: &str,
+) -> BackendResult<()> {
+    let element = Formula::Ident(Identifier::Element(element.to_string()));
+    if let Some(old) = element_mapping.insert(rule.clone(), element.clone()) {
+        if old != element {
+            return Err(BackendError::BadRequest(
+                "Could not infer mapping".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Terms that differ must be the two elements of the substitution.
+fn infer_mapping_between_terms(
+    term_from: &Term,
+    term_to: &Term,
+    from: &RuleIdentifier,
+    to: &RuleIdentifier,
+    element_mapping: &mut BTreeMap<RuleIdentifier, Formula>,
+) -> BackendResult<()> {
+    match (term_from, term_to) {
+        _ if term_from == term_to => Ok(()),
+        (Term::Element(element_from), Term::Element(element_to)) => {
+            add_element_mapping(element_mapping, from, element_from)?;
+            add_element_mapping(element_mapping, to, element_to)
+        }
+        (
+            Term::Function {
+                name: name_from,
+                args: args_from,
+            },
+            Term::Function {
+                name: name_to,
+                args: args_to,
+            },
+        ) if name_from == name_to && args_from.len() == args_to.len() => {
+            for (arg_from, arg_to) in args_from.iter().zip(args_to.iter()) {
+                infer_mapping_between_terms(arg_from, arg_to, from, to, element_mapping)?;
+            }
+            Ok(())
+        }
+        _ => Err(BackendError::BadRequest(
+            "Could not infer mapping".to_string(),
+        )),
+    }
+}
+
+// Renames the bound variables of both bodies to the same fresh element.
+fn rename_bound(
+    identifier_from: &Identifier,
+    formula_from: &Formula,
+    identifier_to: &Identifier,
+    formula_to: &Formula,
+) -> BackendResult<(String, Formula, Formula)> {
+    let (Identifier::Element(name_from), Identifier::Element(name_to)) =
+        (identifier_from, identifier_to)
+    else {
+        return Err(BackendError::BadRequest(
+            "Variable of quantifier must be an element".to_string(),
+        ));
+    };
+    let mut avoid = formula_from.elements();
+    avoid.extend(formula_to.elements());
+    let fresh = fresh_name(name_from, &avoid);
+    let renamed_from = formula_from.apply_substitution(name_from, &fresh)?;
+    let renamed_to = formula_to.apply_substitution(name_to, &fresh)?;
+    Ok((fresh, renamed_from, renamed_to))
+}
+
 fn infer_mapping_between_formula(
     formula_from: &Formula,
     formula_to: &Formula,
@@ -38,16 +129,15 @@ fn infer_mapping_between_formula(
     element_mapping: &mut BTreeMap<RuleIdentifier, Formula>,
 ) -> BackendResult<()> {
     if formula_from == formula_to {
+        // The substitution had no effect, any elements not mapped yet will do.
         match (from, to) {
             (RuleIdentifier::Element(from_str), RuleIdentifier::Element(to_str)) => {
-                element_mapping.insert(
-                    from.clone(),
-                    Formula::Ident(Identifier::Element(from_str.clone())),
-                );
-                element_mapping.insert(
-                    to.clone(),
-                    Formula::Ident(Identifier::Element(to_str.clone())),
-                );
+                element_mapping
+                    .entry(from.clone())
+                    .or_insert_with(|| Formula::Ident(Identifier::Element(from_str.clone())));
+                element_mapping
+                    .entry(to.clone())
+                    .or_insert_with(|| Formula::Ident(Identifier::Element(to_str.clone())));
             }
             _ => {
                 return Err(BackendError::BadRequest(
@@ -87,31 +177,32 @@ fn infer_mapping_between_formula(
                     element_mapping,
                 )?;
             } else {
-                // situations like this:
-                // formula_from : A = forall x. p(x)
-                // formula_to : B = forall y. p(y)
-                // we have a ftion. therefore A[from -> to] = B
-                // Therefore we have that from = x, to = y
-
-                if let Some(old) =
-                    element_mapping.insert(from.clone(), Formula::Ident(identifier_from.clone()))
-                {
-                    if old != Formula::Ident(identifier_from.clone()) {
-                        return Err(BackendError::BadRequest(
-                            "Could not infer mapping".to_string(),
-                        ));
-                    }
-                }
-
-                if let Some(old) =
-                    element_mapping.insert(to.clone(), Formula::Ident(identifier_to.clone()))
+                // The bound variables differ, e.g. because the substitution renamed one
+                // to avoid a capture. Compare the bodies with a common name for it.
+                let (fresh, renamed_from, renamed_to) = rename_bound(
+                    identifier_from,
+                    sub_formula_from,
+                    identifier_to,
+                    sub_formula_to,
+                )?;
+                let mut renamed_mapping = element_mapping.clone();
+                infer_mapping_between_formula(
+                    &renamed_from,
+                    &renamed_to,
+                    from,
+                    to,
+                    &mut renamed_mapping,
+                )?;
+                // A bound variable can not be the substituted element.
+                let fresh = Formula::Ident(Identifier::Element(fresh));
+                if renamed_mapping.get(from) == Some(&fresh)
+                    || renamed_mapping.get(to) == Some(&fresh)
                 {
-                    if old != Formula::Ident(identifier_to.clone()) {
-                        return Err(BackendError::BadRequest(
-                            "Could not infer mapping".to_string(),
-                        ));
-                    }
+                    return Err(BackendError::BadRequest(
+                        "Could not infer mapping".to_string(),
+                    ));
                 }
+                *element_mapping = renamed_mapping;
             }
         }
         (
@@ -135,30 +226,25 @@ fn infer_mapping_between_formula(
                 ));
             }
 
-            for (ident_from, ident_to) in identifiers_from.iter().zip(identifiers_to.iter()) {
-                if let Some(old) =
-                    element_mapping.insert(from.clone(), Formula::Ident(ident_from.clone()))
-                {
-                    if old != Formula::Ident(ident_from.clone()) {
-                        return Err(BackendError::BadRequest(
-                            "Could not infer mapping".to_string(),
-                        ));
-                    }
-                }
-
-                if let Some(old) =
-                    element_mapping.insert(to.clone(), Formula::Ident(ident_to.clone()))
-                {
-                    if old != Formula::Ident(ident_to.clone()) {
-                        return Err(BackendError::BadRequest(
-                            "Could not infer mapping".to_string(),
-                        ));
-                    }
-                }
+            for (term_from, term_to) in identifiers_from.iter().zip(identifiers_to.iter()) {
+                infer_mapping_between_terms(term_from, term_to, from, to, element_mapping)?;
             }
         }
+        (
+            Formula::Eq {
+                lhs: lhs_from,
+                rhs: rhs_from,
+            },
+            Formula::Eq {
+                lhs: lhs_to,
+                rhs: rhs_to,
+            },
+        ) => {
+            infer_mapping_between_terms(lhs_from, lhs_to, from, to, element_mapping)?;
+            infer_mapping_between_terms(rhs_from, rhs_to, from, to, element_mapping)?;
+        }
         (lhs, rhs) => {
-            if lhs != rhs {
+            if !alpha_equivalent(lhs, rhs) {
                 return Err(BackendError::BadRequest(
                     "Could not infer mapping".to_string(),
                 ));
@@ -168,6 +254,227 @@ fn infer_mapping_between_formula(
     Ok(())
 }
 
+// The term `a` with `a[x := s] = from` and `a[x := t] = to`.
+fn abstract_term(from: &Term, to: &Term, s: &str, t: &str, x: &str) -> BackendResult<Term> {
+    match (from, to) {
+        _ if from == to => Ok(from.clone()),
+        (Term::Element(element_from), Term::Element(element_to))
+            if element_from == s && element_to == t =>
+        {
+            Ok(Term::Element(x.to_string()))
+        }
+        (
+            Term::Function {
+                name: name_from,
+                args: args_from,
+            },
+            Term::Function {
+                name: name_to,
+                args: args_to,
+            },
+        ) if name_from == name_to && args_from.len() == args_to.len() => Ok(Term::Function {
+            name: name_from.clone(),
+            args: args_from
+                .iter()
+                .zip(args_to.iter())
+                .map(|(arg_from, arg_to)| abstract_term(arg_from, arg_to, s, t, x))
+                .collect::<BackendResult<_>>()?,
+        }),
+        _ => Err(BackendError::BadRequest(
+            "Could not infer mapping".to_string(),
+        )),
+    }
+}
+
+// The formula `A` with `A[x := s] = from` and `A[x := t] = to`. Occurrences of `s` in
+// `from` that are `t` in `to` become `x`, everything else has to agree.
+fn abstract_formula(
+    from: &Formula,
+    to: &Formula,
+    s: &str,
+    t: &str,
+    x: &str,
+) -> BackendResult<Formula> {
+    let abstract_binary = |lhs_from: &Formula, lhs_to, rhs_from: &Formula, rhs_to| {
+        Ok::<_, BackendError>((
+            Box::new(abstract_formula(lhs_from, lhs_to, s, t, x)?),
+            Box::new(abstract_formula(rhs_from, rhs_to, s, t, x)?),
+        ))
+    };
+    match (from, to) {
+        (
+            Formula::And {
+                lhs: lhs_from,
+                rhs: rhs_from,
+            },
+            Formula::And {
+                lhs: lhs_to,
+                rhs: rhs_to,
+            },
+        ) => {
+            let (lhs, rhs) = abstract_binary(lhs_from, lhs_to, rhs_from, rhs_to)?;
+            Ok(Formula::And { lhs, rhs })
+        }
+        (
+            Formula::Or {
+                lhs: lhs_from,
+                rhs: rhs_from,
+            },
+            Formula::Or {
+                lhs: lhs_to,
+                rhs: rhs_to,
+            },
+        ) => {
+            let (lhs, rhs) = abstract_binary(lhs_from, lhs_to, rhs_from, rhs_to)?;
+            Ok(Formula::Or { lhs, rhs })
+        }
+        (
+            Formula::Imp {
+                lhs: lhs_from,
+                rhs: rhs_from,
+            },
+            Formula::Imp {
+                lhs: lhs_to,
+                rhs: rhs_to,
+            },
+        ) => {
+            let (lhs, rhs) = abstract_binary(lhs_from, lhs_to, rhs_from, rhs_to)?;
+            Ok(Formula::Imp { lhs, rhs })
+        }
+        (Formula::Not(formula_from), Formula::Not(formula_to)) => Ok(Formula::Not(Box::new(
+            abstract_formula(formula_from, formula_to, s, t, x)?,
+        ))),
+        (
+            Formula::Forall {
+                identifier: identifier_from,
+                formula: formula_from,
+            },
+            Formula::Forall {
+                identifier: identifier_to,
+                formula: formula_to,
+            },
+        ) if identifier_from == identifier_to
+            && !matches!(identifier_from, Identifier::Element(e) if e == s || e == t) =>
+        {
+            Ok(Formula::Forall {
+                identifier: identifier_from.clone(),
+                formula: Box::new(abstract_formula(formula_from, formula_to, s, t, x)?),
+            })
+        }
+        (
+            Formula::Exists {
+                identifier: identifier_from,
+                formula: formula_from,
+            },
+            Formula::Exists {
+                identifier: identifier_to,
+                formula: formula_to,
+            },
+        ) if identifier_from == identifier_to
+            && !matches!(identifier_from, Identifier::Element(e) if e == s || e == t) =>
+        {
+            Ok(Formula::Exists {
+                identifier: identifier_from.clone(),
+                formula: Box::new(abstract_formula(formula_from, formula_to, s, t, x)?),
+            })
+        }
+        (
+            Formula::Predicate {
+                identifier: identifier_from,
+                identifiers: identifiers_from,
+            },
+            Formula::Predicate {
+                identifier: identifier_to,
+                identifiers: identifiers_to,
+            },
+        ) if identifier_from == identifier_to && identifiers_from.len() == identifiers_to.len() => {
+            Ok(Formula::Predicate {
+                identifier: identifier_from.clone(),
+                identifiers: identifiers_from
+                    .iter()
+                    .zip(identifiers_to.iter())
+                    .map(|(term_from, term_to)| abstract_term(term_from, term_to, s, t, x))
+                    .collect::<BackendResult<_>>()?,
+            })
+        }
+        (
+            Formula::Eq {
+                lhs: lhs_from,
+                rhs: rhs_from,
+            },
+            Formula::Eq {
+                lhs: lhs_to,
+                rhs: rhs_to,
+            },
+        ) => Ok(Formula::Eq {
+            lhs: abstract_term(lhs_from, lhs_to, s, t, x)?,
+            rhs: abstract_term(rhs_from, rhs_to, s, t, x)?,
+        }),
+        _ if alpha_equivalent(from, to) => Ok(from.clone()),
+        _ => Err(BackendError::BadRequest(
+            "Could not infer mapping".to_string(),
+        )),
+    }
+}
+
+// A formula that only occurs substituted, e.g. `A` in `A[x := s]` and `A[x := t]` of
+// `EqElim`, is abstracted from two of its instances once `s` and `t` are known.
+fn infer_abstraction(
+    instances: &[(&Formula, &RuleFormula)],
+    formula_mapping: &mut BTreeMap<RuleIdentifier, Formula>,
+    element_mapping: &mut BTreeMap<RuleIdentifier, Formula>,
+) -> BackendResult<()> {
+    let element =
+        |identifier: &RuleIdentifier, element_mapping: &BTreeMap<_, _>| match element_mapping
+            .get(identifier)
+        {
+            Some(Formula::Ident(Identifier::Element(e))) => Some(e.clone()),
+            _ => None,
+        };
+    for (i, (formula_s, rule_s)) in instances.iter().enumerate() {
+        for (formula_t, rule_t) in instances.iter().skip(i + 1) {
+            let (
+                RuleFormula::Substitution {
+                    identifier,
+                    lhs: x,
+                    rhs: s,
+                },
+                RuleFormula::Substitution {
+                    identifier: identifier_t,
+                    lhs: x_t,
+                    rhs: t,
+                },
+            ) = (rule_s, rule_t)
+            else {
+                continue;
+            };
+            if identifier != identifier_t || x != x_t || formula_mapping.contains_key(identifier) {
+                continue;
+            }
+            let (Some(s), Some(t)) = (element(s, element_mapping), element(t, element_mapping))
+            else {
+                continue;
+            };
+            let name = match element(x, element_mapping) {
+                Some(name) => name,
+                None => {
+                    let mut avoid = formula_s.elements();
+                    avoid.extend(formula_t.elements());
+                    avoid.extend([s.clone(), t.clone()]);
+                    let RuleIdentifier::Element(x) = x else {
+                        continue;
+                    };
+                    fresh_name(x, &avoid)
+                }
+            };
+            let abstraction = abstract_formula(formula_s, formula_t, &s, &t, &name)?;
+            add_element_mapping(element_mapping, x, &name)?;
+            add_mapping(formula_mapping, identifier, &abstraction)?;
+        }
+    }
+    Ok(())
+}
+
 fn infer_mapping_formula(
     formula: &Formula,
     rule: &RuleFormula,
@@ -207,6 +514,27 @@ fn infer_mapping_formula(
             add_mapping(formula_mapping, rule_identifier, formula)?;
         }
         (Formula::False, RuleFormula::False) | (Formula::True, RuleFormula::True) => {}
+        (
+            Formula::Eq {
+                lhs: Term::Element(lhs),
+                rhs: Term::Element(rhs),
+            },
+            RuleFormula::Eq {
+                lhs: r_lhs,
+                rhs: r_rhs,
+            },
+        ) => {
+            add_mapping(
+                element_mapping,
+                r_lhs,
+                &Formula::Ident(Identifier::Element(lhs.clone())),
+            )?;
+            add_mapping(
+                element_mapping,
+                r_rhs,
+                &Formula::Ident(Identifier::Element(rhs.clone())),
+            )?;
+        }
         (
             Formula::Exists {
                 identifier,
@@ -242,8 +570,9 @@ fn infer_mapping_formula(
                 rhs,
             },
         ) => {
-            if let Some(mapped_formula) = formula_mapping.get(&identifier) {
-                infer_mapping_between_formula(mapped_formula, formula, lhs, rhs, element_mapping);
+            // An unbound formula is left to `infer_abstraction`.
+            if let Some(mapped_formula) = formula_mapping.get(identifier) {
+                infer_mapping_between_formula(mapped_formula, formula, lhs, rhs, element_mapping)?;
             }
         }
         (_, _) => {
@@ -269,19 +598,74 @@ pub fn infer_mapping_stmt(
     )?;
 
     if let Some(lhs_rule) = &rule.lhs {
-        if let Some(mapped_formula) = formula_mapping.get(lhs_rule) {
-            if !statment.lhs.contains(mapped_formula) {
+        if let Ok(mapped_formula) = get_formula(lhs_rule, formula_mapping) {
+            if !statment
+                .lhs
+                .iter()
+                .any(|f| alpha_equivalent(f, &mapped_formula))
+            {
                 return Err(BackendError::BadRequest(format!(
                     "Could not infer mapping. Rhs ({}) is not present in lhs.",
                     mapped_formula
                 )));
-
             }
         }
     };
     Ok(())
 }
 
+/// Turns inferred element mappings into a substitution.
+pub fn element_substitution(
+    element_mapping: BTreeMap<RuleIdentifier, Formula>,
+) -> BackendResult<BTreeMap<RuleIdentifier, String>> {
+    element_mapping
+        .into_iter()
+        .map(|(k, v)| match v {
+            Formula::Ident(Identifier::Element(s)) => Ok((k, s)),
+            v => Err(BackendError::BadRequest(format!(
+                "{} must be mapped to an element, not to {}",
+                k, v
+            ))),
+        })
+        .collect()
+}
+
+impl Statement {
+    /// Infers the mapping of the identifiers from the conclusion of the rule, on top of
+    /// the given partial mapping. Returns the identifiers that are still unbound, e.g. the
+    /// eliminated formula of `ImplElim`.
+    pub fn infer_rule_mapping(
+        &self,
+        rule: &DerivationRule,
+        mapping: &mut BTreeMap<RuleIdentifier, Formula>,
+        substitution: &mut BTreeMap<RuleIdentifier, String>,
+    ) -> BackendResult<BTreeSet<RuleIdentifier>> {
+        self.matches_conclusion(rule)?;
+        let mut element_mapping = substitution
+            .iter()
+            .map(|(k, v)| (k.clone(), Formula::Ident(Identifier::Element(v.clone()))))
+            .collect::<BTreeMap<_, _>>();
+        if let RuleFormula::Substitution { identifier, .. } = &rule.conclusion.formula {
+            // The formula is the goal itself, `apply_rule` substitutes it back.
+            mapping
+                .entry(identifier.clone())
+                .or_insert_with(|| self.formula.clone());
+        } else {
+            infer_mapping_formula(
+                &self.formula,
+                &rule.conclusion.formula,
+                mapping,
+                &mut element_mapping,
+            )?;
+        }
+        *substitution = element_substitution(element_mapping)?;
+
+        let mut unbound = rule.identifiers();
+        unbound.retain(|i| !mapping.contains_key(i) && !substitution.contains_key(i));
+        Ok(unbound)
+    }
+}
+
 impl Node {
     pub fn infer_mapping(
         self,
@@ -296,14 +680,7 @@ impl Node {
         let mut formula_mapping = BTreeMap::<RuleIdentifier, Formula>::new();
         let mut element_mapping = BTreeMap::<RuleIdentifier, Formula>::new();
 
-        for i in 0..5 {
-            infer_mapping_stmt(
-                &self.statement,
-                &applied_rule.conclusion,
-                &mut formula_mapping,
-                &mut element_mapping,
-            )?;
-
+        for _ in 0..5 {
             let child_nodes = self
                 .premisses
                 .iter()
@@ -318,10 +695,27 @@ impl Node {
                 .map(|node| node.statement.clone())
                 .collect::<Vec<_>>();
 
+            // The premisses come first, they bind the elements a substituted conclusion
+            // is abstracted with.
             for (premiss, rule) in premisses.iter().zip(applied_rule.premises.iter()) {
                 infer_mapping_stmt(premiss, rule, &mut formula_mapping, &mut element_mapping)?;
             }
 
+            let instances = premisses
+                .iter()
+                .zip(applied_rule.premises.iter())
+                .chain([(&self.statement, &applied_rule.conclusion)])
+                .map(|(statement, rule)| (&statement.formula, &rule.formula))
+                .collect::<Vec<_>>();
+            infer_abstraction(&instances, &mut formula_mapping, &mut element_mapping)?;
+
+            infer_mapping_stmt(
+                &self.statement,
+                &applied_rule.conclusion,
+                &mut formula_mapping,
+                &mut element_mapping,
+            )?;
+
             let mut all_mapped_identifiers = formula_mapping.keys().collect::<BTreeSet<_>>();
             all_mapped_identifiers.extend(element_mapping.keys());
 
@@ -342,33 +736,395 @@ impl Node {
     }
 }
 
-pub fn check_tree(root: Uuid, all_nodes: &Vec<Node>) -> BackendResult<()> {
-    let root_node = all_nodes
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NodeError {
+    pub node: Uuid,
+    pub rule: Option<Rules>,
+    pub message: String,
+    pub expected: Vec<String>,
+    pub actual: Vec<String>,
+    pub side_condition: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TreeValidation {
+    pub valid: bool,
+    pub errors: Vec<NodeError>,
+}
+
+fn reason(err: BackendError) -> String {
+    match err {
+        BackendError::BadRequest(s) | BackendError::Unknown(s) => s,
+        err => err.to_string(),
+    }
+}
+
+pub fn check_node(node: &Node, all_nodes: &Vec<Node>) -> Result<(), NodeError> {
+    let rule = Rules::get_rule(&node.rule);
+    let premisses = node
+        .premisses
+        .iter()
+        .filter_map(|id| all_nodes.iter().find(|n| n.name == *id))
+        .map(|n| n.statement.to_string())
+        .collect::<Vec<_>>();
+    let schematic = rule
+        .premises
         .iter()
-        .find(|node| node.name == root)
-        .ok_or_else(|| BackendError::BadRequest("Could not find root node".to_string()))?;
-    let rule = Rules::get_rule(&root_node.rule);
-    let identifier = rule.identifiers();
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>();
 
-    let mut formula_mapping = BTreeMap::<RuleIdentifier, Formula>::new();
-    let mut element_mapping = BTreeMap::<RuleIdentifier, Formula>::new();
+    let error =
+        |message: String, expected: Vec<String>, side_condition: Option<String>| NodeError {
+            node: node.name,
+            rule: Some(node.rule.clone()),
+            message,
+            expected,
+            actual: premisses.clone(),
+            side_condition,
+        };
 
-    for i in 0..5 {
-        (formula_mapping, element_mapping) = root_node.clone().infer_mapping(all_nodes)?;
+    if premisses.len() != node.premisses.len() {
+        return Err(error(
+            "Could not find all premisses".to_string(),
+            schematic,
+            None,
+        ));
+    }
+    if premisses.len() != rule.premises.len() {
+        return Err(error(
+            format!(
+                "The rule expects {} premisses but {} were given",
+                rule.premises.len(),
+                premisses.len()
+            ),
+            schematic,
+            None,
+        ));
     }
 
-    let mut substitution = element_mapping
+    let (formula_mapping, element_mapping) = node
+        .clone()
+        .infer_mapping(all_nodes)
+        .map_err(|e| error(reason(e), schematic.clone(), None))?;
+
+    let substitution = element_mapping
         .iter()
         .map(|(k, v)| match v {
-            Formula::Ident(Identifier::Element(s)) => (k.clone(), s.clone()),
-            _ => panic!("Could not translate element mapping to substitution {}", v),
+            Formula::Ident(Identifier::Element(s)) => Ok((k.clone(), s.clone())),
+            _ => Err(error(
+                format!("{} must be mapped to an element, not to {}", k, v),
+                schematic.clone(),
+                None,
+            )),
         })
-        .collect::<BTreeMap<RuleIdentifier, String>>();
+        .collect::<Result<BTreeMap<RuleIdentifier, String>, NodeError>>()?;
+
+    let instantiated = node
+        .statement
+        .instantiate_premisses(&rule, &formula_mapping, &substitution)
+        .map_err(|e| error(reason(e), schematic.clone(), None))?;
+    let expected = instantiated
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+
+    node.statement
+        .matches_conclusion(&rule)
+        .map_err(|e| error(reason(e), expected.clone(), None))?;
+
+    // The premisses have to be the instantiated ones, with exactly the assumptions of
+    // the conclusion and the discharged one.
+    let actual = node
+        .premisses
+        .iter()
+        .filter_map(|id| all_nodes.iter().find(|n| n.name == *id))
+        .map(|n| &n.statement);
+    for (i, (expected_premiss, actual_premiss)) in instantiated.iter().zip(actual).enumerate() {
+        if !alpha_equivalent(&expected_premiss.formula, &actual_premiss.formula) {
+            return Err(error(
+                format!("Premiss {} does not match the rule", i + 1),
+                expected,
+                None,
+            ));
+        }
+        if !subset(&expected_premiss.lhs, &actual_premiss.lhs)
+            || !subset(&actual_premiss.lhs, &expected_premiss.lhs)
+        {
+            return Err(error(
+                format!("The assumptions of premiss {} do not match the rule", i + 1),
+                expected,
+                None,
+            ));
+        }
+    }
+    node.statement
+        .check_side_conditions(&rule, &substitution)
+        .map_err(|e| {
+            error(
+                "A side condition of the rule is violated".to_string(),
+                expected.clone(),
+                Some(reason(e)),
+            )
+        })?;
+    Ok(())
+}
 
-    root_node.statement.can_apply(&rule, &substitution)?;
+fn validate_node(
+    id: Uuid,
+    all_nodes: &Vec<Node>,
+    branch: &mut BTreeSet<Uuid>,
+    errors: &mut Vec<NodeError>,
+) {
+    let Some(node) = all_nodes.iter().find(|node| node.name == id) else {
+        errors.push(NodeError {
+            node: id,
+            rule: None,
+            message: "Could not find node".to_string(),
+            expected: vec![],
+            actual: vec![],
+            side_condition: None,
+        });
+        return;
+    };
+    if !branch.insert(id) {
+        errors.push(NodeError {
+            node: id,
+            rule: Some(node.rule.clone()),
+            message: "The node is its own premiss".to_string(),
+            expected: vec![],
+            actual: vec![],
+            side_condition: None,
+        });
+        return;
+    }
+
+    if let Err(err) = check_node(node, all_nodes) {
+        errors.push(err);
+    }
+    for premiss in node.premisses.iter() {
+        validate_node(*premiss, all_nodes, branch, errors);
+    }
+    branch.remove(&id);
+}
+
+/// Checks every node reachable from the root and reports all invalid rule applications.
+pub fn validate_tree(root: Uuid, all_nodes: &Vec<Node>) -> TreeValidation {
+    let mut errors = Vec::new();
+    validate_node(root, all_nodes, &mut BTreeSet::new(), &mut errors);
+    TreeValidation {
+        valid: errors.is_empty(),
+        errors,
+    }
+}
 
-    for node in root_node.premisses.iter() {
-        check_tree(*node, all_nodes)?;
+pub fn check_tree(root: Uuid, all_nodes: &Vec<Node>) -> BackendResult<()> {
+    let report = validate_tree(root, all_nodes);
+    if !report.valid {
+        return Err(BackendError::InvalidTree(Box::new(report)));
     }
     Ok(())
 }
+
+// Copies the subtree with the additional assumptions in every node.
+fn copy_weakened(
+    id: Uuid,
+    all_nodes: &Vec<Node>,
+    extra: &[Formula],
+    copies: &mut Vec<Node>,
+    branch: &mut BTreeSet<Uuid>,
+) -> BackendResult<Uuid> {
+    let Some(node) = all_nodes.iter().find(|node| node.name == id) else {
+        return Ok(id);
+    };
+    if !branch.insert(id) {
+        return Err(BackendError::BadRequest(
+            "The fragments form a cycle".to_string(),
+        ));
+    }
+    let premisses = node
+        .premisses
+        .iter()
+        .map(|premiss| copy_weakened(*premiss, all_nodes, extra, copies, branch))
+        .collect::<BackendResult<Vec<_>>>()?;
+    branch.remove(&id);
+
+    let mut statement = node.statement.clone();
+    for f in extra {
+        if !contains(&statement.lhs, f) {
+            statement.lhs.push(f.clone());
+        }
+    }
+    let copy = Node {
+        name: Uuid::new_v4(),
+        statement,
+        rule: node.rule.clone(),
+        premisses,
+    };
+    let name = copy.name;
+    copies.push(copy);
+    Ok(name)
+}
+
+/// Joins fragments of a derivation, e.g. one built bottom-up with open goals and others
+/// derived top-down. Every open goal is closed by a node with the same formula and at
+/// most the assumptions of the goal. Only the nodes reachable from the root are kept.
+pub fn merge_fragments(
+    root: Uuid,
+    all_nodes: &Vec<Node>,
+    goals: &[Goal],
+) -> BackendResult<Vec<Node>> {
+    let mut merged = all_nodes.clone();
+    let mut closed_by = BTreeMap::new();
+    for goal in goals {
+        let closing = all_nodes
+            .iter()
+            .filter(|node| {
+                alpha_equivalent(&node.statement.formula, &goal.statement.formula)
+                    && subset(&node.statement.lhs, &goal.statement.lhs)
+            })
+            // Prefer a fragment that needs the fewest additional assumptions.
+            .min_by_key(|node| {
+                goal.statement
+                    .lhs
+                    .iter()
+                    .filter(|f| !contains(&node.statement.lhs, f))
+                    .count()
+            })
+            .ok_or_else(|| {
+                BackendError::BadRequest(format!(
+                    "No fragment derives the open goal {}",
+                    goal.statement
+                ))
+            })?;
+        let extra = goal
+            .statement
+            .lhs
+            .iter()
+            .filter(|f| !contains(&closing.statement.lhs, f))
+            .cloned()
+            .collect::<Vec<_>>();
+        let name = if extra.is_empty() {
+            closing.name
+        } else {
+            copy_weakened(
+                closing.name,
+                all_nodes,
+                &extra,
+                &mut merged,
+                &mut BTreeSet::new(),
+            )?
+        };
+        closed_by.insert(goal.name, name);
+    }
+
+    for node in merged.iter_mut() {
+        for premiss in node.premisses.iter_mut() {
+            if let Some(name) = closed_by.get(premiss) {
+                *premiss = *name;
+            }
+        }
+    }
+
+    let mut reachable = BTreeSet::new();
+    let mut pending = vec![root];
+    while let Some(id) = pending.pop() {
+        if !reachable.insert(id) {
+            continue;
+        }
+        if let Some(node) = merged.iter().find(|node| node.name == id) {
+            pending.extend(node.premisses.iter().cloned());
+        }
+    }
+    merged.retain(|node| reachable.contains(&node.name));
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::derivation::search::SearchLimits;
+    use crate::lib::LogicParser;
+
+    fn statement(lhs: &[&str], formula: &str) -> Statement {
+        Statement {
+            lhs: lhs
+                .iter()
+                .map(|f| LogicParser::parse_input(f).unwrap())
+                .collect(),
+            formula: LogicParser::parse_input(formula).unwrap(),
+            sidecondition: vec![],
+        }
+    }
+
+    fn node(statement: Statement, rule: Rules, premisses: &[&Node]) -> Node {
+        Node {
+            name: Uuid::new_v4(),
+            statement,
+            rule,
+            premisses: premisses.iter().map(|n| n.name).collect(),
+        }
+    }
+
+    #[test]
+    fn searched_eq_elim_trees_are_valid() {
+        for (lhs, formula) in [
+            (&["x = y", "p(x)"][..], "p(y)"),
+            (&["x = y", "q(x, x)"][..], "q(x, y)"),
+            (&["x = y", "forall_z r(z, f(x))"][..], "forall_z r(z, f(y))"),
+        ] {
+            let tree = statement(lhs, formula)
+                .search_proof(SearchLimits::default())
+                .unwrap();
+            assert!(tree.nodes.iter().any(|n| n.rule == Rules::EqElim));
+            let validation = validate_tree(tree.root_id, &tree.nodes);
+            assert!(validation.valid, "{formula}: {:?}", validation.errors);
+        }
+    }
+
+    #[test]
+    fn eq_elim_rejects_other_conclusions() {
+        let lhs = ["x = y", "p(x)"];
+        let eq = node(statement(&lhs, "x = y"), Rules::Ax, &[]);
+        let instance = node(statement(&lhs, "p(x)"), Rules::Ax, &[]);
+        for formula in ["p(z)", "q(y)"] {
+            let root = node(statement(&lhs, formula), Rules::EqElim, &[&eq, &instance]);
+            let nodes = vec![eq.clone(), instance.clone(), root.clone()];
+            assert!(check_tree(root.name, &nodes).is_err(), "{formula}");
+        }
+    }
+
+    #[test]
+    fn premisses_need_the_assumptions_of_the_conclusion() {
+        let a = node(statement(&["A"], "A"), Rules::Ax, &[]);
+        let b = node(statement(&["B"], "B"), Rules::Ax, &[]);
+        let root = node(statement(&[], "A and B"), Rules::AndIntro, &[&a, &b]);
+        let nodes = vec![a, b, root.clone()];
+        let validation = validate_tree(root.name, &nodes);
+        assert!(!validation.valid);
+        assert_eq!(validation.errors[0].node, root.name);
+        assert_eq!(validation.errors[0].expected, vec![" |- A", " |- B"]);
+    }
+
+    #[test]
+    fn premisses_have_to_match_the_rule() {
+        let lhs = ["A", "B"];
+        let a = node(statement(&lhs, "A"), Rules::Ax, &[]);
+        let b = node(statement(&lhs, "B"), Rules::Ax, &[]);
+        let root = node(statement(&lhs, "A and A"), Rules::AndIntro, &[&a, &b]);
+        let nodes = vec![a, b, root.clone()];
+        assert!(check_tree(root.name, &nodes).is_err());
+    }
+
+    #[test]
+    fn discharged_assumptions_are_expected() {
+        let a = node(statement(&["A"], "A"), Rules::Ax, &[]);
+        let root = node(statement(&[], "A -> A"), Rules::ImplIntro, &[&a]);
+        let nodes = vec![a.clone(), root.clone()];
+        assert!(check_tree(root.name, &nodes).is_ok());
+
+        let weakened = node(statement(&["A", "B"], "A"), Rules::Ax, &[]);
+        let root = node(statement(&[], "A -> A"), Rules::ImplIntro, &[&weakened]);
+        let nodes = vec![weakened, root.clone()];
+        assert!(check_tree(root.name, &nodes).is_err());
+    }
+}
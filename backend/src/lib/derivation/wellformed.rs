@@ -0,0 +1,259 @@
+use crate::{
+    api::models::{
+        ApplyRuleParams, CreateTreeRequest, ElementMapping, FormulaMapping, ForwardRuleParams,
+        MergeTreeRequest, SessionApplyParams, SideCondition,
+    },
+    error::{BackendError, BackendResult},
+};
+
+use super::{
+    formula::{Formula, Identifier, Term},
+    statement::Statement,
+};
+
+// Words the parser reserves for operators, they can not be used as names.
+const KEYWORDS: [&str; 16] = [
+    "true",
+    "top",
+    "false",
+    "bot",
+    "or",
+    "lor",
+    "and",
+    "land",
+    "not",
+    "neg",
+    "lnot",
+    "to",
+    "rightarrow",
+    "implies",
+    "forall",
+    "exists",
+];
+
+fn bad(reason: String) -> BackendError {
+    BackendError::BadRequest(reason)
+}
+
+// Names follow the grammar: literals start with an uppercase letter, elements,
+// predicates and functions with a lowercase letter.
+fn check_name(name: &str, kind: &str, uppercase: bool) -> BackendResult<()> {
+    let mut chars = name.chars();
+    let first_ok = match chars.next() {
+        Some(c) if uppercase => c.is_ascii_uppercase(),
+        Some(c) => c.is_ascii_lowercase(),
+        None => return Err(bad(format!("The name of a {kind} must not be empty"))),
+    };
+    if !first_ok {
+        let case = if uppercase { "uppercase" } else { "lowercase" };
+        return Err(bad(format!(
+            "The {kind} {name} must start with an {case} letter"
+        )));
+    }
+    if !chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '\'') {
+        return Err(bad(format!(
+            "The {kind} {name} may only contain letters, digits, _ and '"
+        )));
+    }
+    if KEYWORDS.contains(&name) {
+        return Err(bad(format!(
+            "{name} is a keyword and can not name a {kind}"
+        )));
+    }
+    Ok(())
+}
+
+// Predicates and functions take at least one argument, the arity itself is checked by
+// the signature inference.
+fn check_arity(kind: &str, name: &str, arity: usize) -> BackendResult<()> {
+    if arity == 0 {
+        return Err(bad(format!(
+            "The {kind} {name} needs at least one argument"
+        )));
+    }
+    Ok(())
+}
+
+impl Identifier {
+    pub fn well_formed(&self) -> BackendResult<()> {
+        match self {
+            Identifier::Literal(name) => check_name(name, "literal", true),
+            Identifier::Element(name) => check_name(name, "element", false),
+        }
+    }
+}
+
+impl Term {
+    fn check_well_formed(&self) -> BackendResult<()> {
+        match self {
+            Term::Element(name) => check_name(name, "element", false),
+            Term::Function { name, args } => {
+                check_name(name, "function", false)?;
+                check_arity("function", name, args.len())?;
+                args.iter().try_for_each(|arg| arg.check_well_formed())
+            }
+        }
+    }
+}
+
+impl Formula {
+    /// Checks names, arities and quantified variables, so malformed input is rejected
+    /// before it reaches the solver or the rule engine.
+    pub fn well_formed(&self) -> BackendResult<()> {
+        self.check_well_formed()?;
+        self.signature().map(|_| ())
+    }
+
+    fn check_well_formed(&self) -> BackendResult<()> {
+        match self {
+            Formula::And { lhs, rhs } | Formula::Or { lhs, rhs } | Formula::Imp { lhs, rhs } => {
+                lhs.check_well_formed()?;
+                rhs.check_well_formed()
+            }
+            Formula::Not(formula) => formula.check_well_formed(),
+            Formula::True | Formula::False => Ok(()),
+            Formula::Ident(Identifier::Literal(name)) => check_name(name, "literal", true),
+            Formula::Ident(Identifier::Element(name)) => Err(bad(format!(
+                "The element {name} can not be used as a formula"
+            ))),
+            Formula::Forall {
+                identifier,
+                formula,
+            }
+            | Formula::Exists {
+                identifier,
+                formula,
+            } => {
+                match identifier {
+                    Identifier::Element(name) => check_name(name, "element", false)?,
+                    Identifier::Literal(name) => {
+                        return Err(bad(format!(
+                            "Quantifiers must bind an element, not the literal {name}"
+                        )))
+                    }
+                }
+                formula.check_well_formed()
+            }
+            Formula::Predicate {
+                identifier,
+                identifiers,
+            } => {
+                let name = match identifier {
+                    Identifier::Element(name) => name,
+                    Identifier::Literal(name) => {
+                        return Err(bad(format!(
+                            "The predicate {name} must start with a lowercase letter"
+                        )))
+                    }
+                };
+                check_name(name, "predicate", false)?;
+                check_arity("predicate", name, identifiers.len())?;
+                identifiers
+                    .iter()
+                    .try_for_each(|term| term.check_well_formed())
+            }
+            // The equality rules substitute elements, so `f(c) = f(c)` could be stated
+            // but never derived.
+            Formula::Eq { lhs, rhs } => {
+                lhs.check_well_formed()?;
+                rhs.check_well_formed()?;
+                match (lhs, rhs) {
+                    (Term::Element(_), Term::Element(_)) => Ok(()),
+                    _ => Err(bad(format!(
+                        "Equality is only supported between elements, not in {lhs} = {rhs}"
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+impl SideCondition {
+    pub fn well_formed(&self) -> BackendResult<()> {
+        match self {
+            SideCondition::NotFree(pair) => {
+                match (&pair.element, &pair.placeholder) {
+                    (Identifier::Element(_), Identifier::Literal(_)) => {}
+                    _ => {
+                        return Err(bad(format!(
+                            "A side condition relates an element to a literal, not {:?} to {:?}",
+                            pair.element, pair.placeholder
+                        )))
+                    }
+                }
+                pair.element.well_formed()?;
+                pair.placeholder.well_formed()
+            }
+        }
+    }
+}
+
+impl Statement {
+    pub fn well_formed(&self) -> BackendResult<()> {
+        for f in self.lhs.iter() {
+            f.check_well_formed()?;
+        }
+        self.formula.check_well_formed()?;
+        self.sidecondition
+            .iter()
+            .try_for_each(|condition| condition.well_formed())?;
+        self.signature().map(|_| ())
+    }
+}
+
+impl CreateTreeRequest {
+    pub fn well_formed(&self) -> BackendResult<()> {
+        self.nodes
+            .iter()
+            .try_for_each(|node| node.statement.well_formed())
+    }
+}
+
+fn mappings_well_formed(
+    mapping: &[FormulaMapping],
+    substitution: &[ElementMapping],
+) -> BackendResult<()> {
+    for mapping in mapping.iter() {
+        mapping.to.well_formed()?;
+    }
+    for substitution in substitution.iter() {
+        check_name(&substitution.to, "element", false)?;
+    }
+    Ok(())
+}
+
+impl ApplyRuleParams {
+    pub fn well_formed(&self) -> BackendResult<()> {
+        self.statement.well_formed()?;
+        mappings_well_formed(&self.mapping, &self.substitution)
+    }
+}
+
+impl SessionApplyParams {
+    pub fn well_formed(&self) -> BackendResult<()> {
+        mappings_well_formed(&self.mapping, &self.substitution)
+    }
+}
+
+impl ForwardRuleParams {
+    pub fn well_formed(&self) -> BackendResult<()> {
+        for premiss in self.premisses.iter() {
+            premiss.well_formed()?;
+        }
+        for f in self.context.iter().flatten() {
+            f.well_formed()?;
+        }
+        mappings_well_formed(&self.mapping, &self.substitution)
+    }
+}
+
+impl MergeTreeRequest {
+    pub fn well_formed(&self) -> BackendResult<()> {
+        for node in self.nodes.iter() {
+            node.statement.well_formed()?;
+        }
+        self.goals
+            .iter()
+            .try_for_each(|goal| goal.statement.well_formed())
+    }
+}
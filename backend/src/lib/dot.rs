@@ -0,0 +1,123 @@
+use std::collections::BTreeSet;
+
+use uuid::Uuid;
+
+use crate::{
+    api::models::Node,
+    error::{BackendError, BackendResult},
+    lib::db::graph::HintGraph,
+};
+
+fn quote(label: &str) -> String {
+    let escaped = label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+fn render_node(
+    id: Uuid,
+    nodes: &[Node],
+    branch: &mut BTreeSet<Uuid>,
+    rendered: &mut BTreeSet<Uuid>,
+    lines: &mut Vec<String>,
+) -> BackendResult<()> {
+    if !branch.insert(id) {
+        return Err(BackendError::BadRequest(
+            "The derivation tree contains a cycle".to_string(),
+        ));
+    }
+    let node = nodes
+        .iter()
+        .find(|n| n.name == id)
+        .ok_or(BackendError::IdNotFound {
+            entity: "Node".to_string(),
+            id: id.to_string(),
+        })?;
+
+    if rendered.insert(id) {
+        let label = format!("{}\n({:?})", node.statement, node.rule);
+        lines.push(format!(
+            "{} [label={}];",
+            quote(&id.to_string()),
+            quote(&label)
+        ));
+        for premiss in node.premisses.iter() {
+            render_node(*premiss, nodes, branch, rendered, lines)?;
+            lines.push(format!(
+                "{} -> {};",
+                quote(&id.to_string()),
+                quote(&premiss.to_string())
+            ));
+        }
+    }
+    branch.remove(&id);
+    Ok(())
+}
+
+/// Renders a derivation as a DOT digraph with the conclusion at the bottom.
+pub fn tree_to_dot(root: Uuid, nodes: &[Node]) -> BackendResult<String> {
+    let mut lines = Vec::new();
+    render_node(
+        root,
+        nodes,
+        &mut BTreeSet::new(),
+        &mut BTreeSet::new(),
+        &mut lines,
+    )?;
+
+    let mut dot = vec![
+        "digraph derivation {".to_string(),
+        "  rankdir=BT;".to_string(),
+        "  node [shape=box];".to_string(),
+    ];
+    dot.extend(lines.into_iter().map(|line| format!("  {line}")));
+    dot.push("}".to_string());
+    Ok(dot.join("\n"))
+}
+
+/// Renders the hint graph as a DOT digraph. Statements point to their stored
+/// derivations, which point to their premisses. Statements without a derivation are
+/// dashed.
+pub fn hint_graph_to_dot(graph: &HintGraph) -> String {
+    let mut dot = vec![
+        "digraph hints {".to_string(),
+        "  rankdir=BT;".to_string(),
+        "  node [shape=box];".to_string(),
+    ];
+    for (id, statement) in graph.statements.iter() {
+        let mut attributes = vec![format!("label={}", quote(&statement.to_string()))];
+        if *id == graph.root {
+            attributes.push("peripheries=2".to_string());
+        }
+        if graph
+            .derivations
+            .get(id)
+            .is_none_or(|derivations| derivations.is_empty())
+        {
+            attributes.push("style=dashed".to_string());
+        }
+        dot.push(format!(
+            "  {} [{}];",
+            quote(&id.to_string()),
+            attributes.join(", ")
+        ));
+    }
+    for (id, derivations) in graph.derivations.iter() {
+        for derivation in derivations {
+            let step = quote(&format!("{}/{:?}", id, derivation.rule));
+            dot.push(format!(
+                "  {} [label={}, shape=plaintext];",
+                step,
+                quote(&format!("{:?}", derivation.rule))
+            ));
+            dot.push(format!("  {} -> {};", quote(&id.to_string()), step));
+            for premiss in derivation.premisses.iter() {
+                dot.push(format!("  {} -> {};", step, quote(&premiss.to_string())));
+            }
+        }
+    }
+    dot.push("}".to_string());
+    dot.join("\n")
+}
@@ -1,48 +1,116 @@
-use crate::api::{exercise_models::Exercise, formula_models::{Formula, Statement}};
+use std::collections::BTreeSet;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    api::models::{Exercise, Node},
+    error::{BackendError, BackendResult},
+    lib::{
+        derivation::{formula::Formula, statement::Statement},
+        rule::Rules,
+    },
+};
 
 #[derive(ToSchema, Serialize)]
-pub struct Latex<T> where T: Serialize + ToLatex + ToSchema {
+pub struct Latex<T>
+where
+    T: Serialize + ToLatex + ToSchema,
+{
     data: T,
-    latex: String
+    latex: String,
 }
 
-impl<T> From<T> for Latex<T> where T: Serialize + ToLatex + ToSchema {
+impl<T> From<T> for Latex<T>
+where
+    T: Serialize + ToLatex + ToSchema,
+{
     fn from(value: T) -> Self {
-        Self { latex: value.to_latex_str(), data: value }
+        Self {
+            latex: value.to_latex_str(),
+            data: value,
+        }
     }
 }
 
 pub trait ToLatex: Serialize + Sized + ToSchema {
     fn to_latex_str(&self) -> String;
     fn to_latex(self) -> Latex<Self> {
-        Latex { latex: self.to_latex_str(), data: self }
+        Latex {
+            latex: self.to_latex_str(),
+            data: self,
+        }
     }
 }
 
 impl ToLatex for Formula {
     fn to_latex_str(&self) -> String {
-        match self {
-            Formula::And { lhs, rhs } => format!("({} \\land {})", lhs.to_latex_str(), rhs.to_latex_str()),
-            Formula::Or { lhs, rhs } => format!("({} \\lor {})", lhs.to_latex_str(), rhs.to_latex_str()),
-            Formula::Not(f) => format!("\\lnot {}", f.to_latex_str()),
-            Formula::Imp { lhs, rhs } => format!("({} \\rightarrow {})", lhs.to_latex_str(), rhs.to_latex_str()),
-            Formula::True => "\\top".to_string(),
-            Formula::False => "\\bot".to_string(),
-            Formula::Forall { identifier, formula } => format!("\\forall_{} {}", identifier, formula.to_latex_str()),
-            Formula::Exists { identifier, formula } => format!("\\exists_{} {}", identifier, formula.to_latex_str()),
-            Formula::Predicate { identifier, identifiers } => {
-                let vars = identifiers
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                format!("{}({})", identifier, vars)
-            }
-            Formula::Ident(identifier) => identifier.to_string(),
+        formula_to_latex(self, 0, true)
+    }
+}
+
+// Parenthesizes like `Formula::fmt_with`: subformulas binding weaker than required are
+// wrapped, and so are quantifiers that do not end the formula, as their body extends to
+// the right.
+fn formula_to_latex(formula: &Formula, required: u8, rightmost: bool) -> String {
+    let precedence = formula.precedence();
+    let quantifier = matches!(formula, Formula::Forall { .. } | Formula::Exists { .. });
+    let parentheses = precedence < required || (quantifier && !rightmost);
+    let rightmost = rightmost || parentheses;
+    let latex = match formula {
+        Formula::And { lhs, rhs } => format!(
+            "{} \\land {}",
+            formula_to_latex(lhs, precedence, false),
+            formula_to_latex(rhs, precedence + 1, rightmost)
+        ),
+        Formula::Or { lhs, rhs } => format!(
+            "{} \\lor {}",
+            formula_to_latex(lhs, precedence, false),
+            formula_to_latex(rhs, precedence + 1, rightmost)
+        ),
+        Formula::Not(f) => format!("\\lnot {}", formula_to_latex(f, precedence, rightmost)),
+        Formula::Imp { lhs, rhs } => format!(
+            "{} \\rightarrow {}",
+            formula_to_latex(lhs, precedence + 1, false),
+            formula_to_latex(rhs, precedence, rightmost)
+        ),
+        Formula::True => "\\top".to_string(),
+        Formula::False => "\\bot".to_string(),
+        Formula::Forall {
+            identifier,
+            formula,
+        } => format!(
+            "\\forall {}.\\, {}",
+            identifier,
+            formula_to_latex(formula, 0, true)
+        ),
+        Formula::Exists {
+            identifier,
+            formula,
+        } => format!(
+            "\\exists {}.\\, {}",
+            identifier,
+            formula_to_latex(formula, 0, true)
+        ),
+        Formula::Predicate {
+            identifier,
+            identifiers,
+        } => {
+            let vars = identifiers
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{}({})", identifier, vars)
         }
+        Formula::Ident(identifier) => identifier.to_string(),
+        Formula::Eq { lhs, rhs } => format!("{} = {}", lhs, rhs),
+    };
+    if parentheses {
+        format!("({latex})")
+    } else {
+        latex
     }
 }
 
@@ -54,7 +122,7 @@ impl ToLatex for Statement {
             .map(|a| a.to_latex_str())
             .collect::<Vec<_>>()
             .join(", ");
-        format!("{} \\vDash {}", assumptions, self.formula.to_latex_str())
+        format!("{} \\vdash {}", assumptions, self.formula.to_latex_str())
     }
 }
 
@@ -62,4 +130,171 @@ impl ToLatex for Exercise {
     fn to_latex_str(&self) -> String {
         self.exercise.to_latex_str()
     }
-}
\ No newline at end of file
+}
+
+impl ToLatex for Rules {
+    fn to_latex_str(&self) -> String {
+        match self {
+            Rules::Ax => "\\textsc{Ax}",
+            Rules::ImplIntro => "{\\rightarrow}I",
+            Rules::ImplElim => "{\\rightarrow}E",
+            Rules::FalseElim => "\\bot E",
+            Rules::NotIntro => "\\lnot I",
+            Rules::NotElim => "\\lnot E",
+            Rules::AndIntro => "\\land I",
+            Rules::AndElimL => "\\land EL",
+            Rules::AndElimR => "\\land ER",
+            Rules::OrIntroL => "\\lor IL",
+            Rules::OrIntroR => "\\lor IR",
+            Rules::OrElim => "\\lor E",
+            Rules::ForallElim => "\\forall E",
+            Rules::ForallIntro => "\\forall I",
+            Rules::ExistsElim => "\\exists E",
+            Rules::ExistsIntro => "\\exists I",
+            Rules::AlphaExists => "\\alpha\\exists",
+            Rules::AlphaForall => "\\alpha\\forall",
+            Rules::EqIntro => "{=}I",
+            Rules::EqElim => "{=}E",
+            Rules::Raa => "RAA",
+            Rules::Lem => "LEM",
+            Rules::DoubleNegElim => "\\neg\\neg E",
+        }
+        .to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub enum LatexStyle {
+    #[default]
+    Bussproofs,
+    Ebproof,
+}
+
+impl LatexStyle {
+    fn package(&self) -> &'static str {
+        match self {
+            LatexStyle::Bussproofs => "bussproofs",
+            LatexStyle::Ebproof => "ebproof",
+        }
+    }
+}
+
+fn render_node(
+    id: Uuid,
+    nodes: &Vec<Node>,
+    style: LatexStyle,
+    visited: &mut BTreeSet<Uuid>,
+    lines: &mut Vec<String>,
+) -> BackendResult<()> {
+    if !visited.insert(id) {
+        return Err(BackendError::BadRequest(
+            "The derivation tree contains a cycle".to_string(),
+        ));
+    }
+    let node = nodes
+        .iter()
+        .find(|n| n.name == id)
+        .ok_or(BackendError::IdNotFound {
+            entity: "Node".to_string(),
+            id: id.to_string(),
+        })?;
+
+    for premiss in node.premisses.iter() {
+        render_node(*premiss, nodes, style, visited, lines)?;
+    }
+
+    let label = node.rule.to_latex_str();
+    let statement = node.statement.to_latex_str();
+    match style {
+        LatexStyle::Bussproofs => {
+            let inference = match node.premisses.len() {
+                0 => {
+                    lines.push("\\AxiomC{}".to_string());
+                    "UnaryInfC"
+                }
+                1 => "UnaryInfC",
+                2 => "BinaryInfC",
+                3 => "TrinaryInfC",
+                4 => "QuaternaryInfC",
+                5 => "QuinaryInfC",
+                _ => {
+                    return Err(BackendError::BadRequest(
+                        "bussproofs supports at most five premisses".to_string(),
+                    ))
+                }
+            };
+            lines.push(format!("\\RightLabel{{\\scriptsize $({label})$}}"));
+            lines.push(format!("\\{inference}{{${statement}$}}"));
+        }
+        LatexStyle::Ebproof => {
+            lines.push(format!(
+                "\\infer{}[$({label})$]{{{statement}}}",
+                node.premisses.len()
+            ));
+        }
+    }
+    visited.remove(&id);
+    Ok(())
+}
+
+/// Renders a derivation as a `prooftree` environment of the given package.
+pub fn tree_to_latex(root: Uuid, nodes: &Vec<Node>, style: LatexStyle) -> BackendResult<String> {
+    let mut lines = Vec::new();
+    render_node(root, nodes, style, &mut BTreeSet::new(), &mut lines)?;
+
+    let mut proof = vec!["\\begin{prooftree}".to_string()];
+    proof.extend(lines.into_iter().map(|line| format!("  {line}")));
+    proof.push("\\end{prooftree}".to_string());
+    Ok(proof.join("\n"))
+}
+
+/// Wraps a rendered derivation into a standalone document.
+pub fn latex_document(proof: &str, style: LatexStyle) -> String {
+    format!(
+        "\\documentclass{{article}}\n\\usepackage{{amsmath, amssymb}}\n\\usepackage{{{}}}\n\n\\begin{{document}}\n{}\n\\end{{document}}\n",
+        style.package(),
+        proof
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::LogicParser;
+
+    fn latex(formula: &str) -> String {
+        LogicParser::parse_input(formula).unwrap().to_latex_str()
+    }
+
+    #[test]
+    fn quantifiers_on_the_left_are_parenthesized() {
+        assert_eq!(
+            latex("(forall_x p(x)) -> q(c)"),
+            "(\\forall x.\\, p(x)) \\rightarrow q(c)"
+        );
+        assert_eq!(
+            latex("forall_x p(x) -> q(c)"),
+            "\\forall x.\\, p(x) \\rightarrow q(c)"
+        );
+        assert_eq!(
+            latex("(exists_x p(x)) and A"),
+            "(\\exists x.\\, p(x)) \\land A"
+        );
+    }
+
+    #[test]
+    fn negation_parenthesizes_compound_formulas() {
+        assert_eq!(latex("not (A and B)"), "\\lnot (A \\land B)");
+        assert_eq!(latex("not A and B"), "\\lnot A \\land B");
+        assert_eq!(
+            latex("(not forall_x p(x)) or A"),
+            "\\lnot (\\forall x.\\, p(x)) \\lor A"
+        );
+    }
+
+    #[test]
+    fn binary_operators_follow_the_parser() {
+        assert_eq!(latex("(A -> B) -> C"), "(A \\rightarrow B) \\rightarrow C");
+        assert_eq!(latex("A or B and C"), "A \\lor B \\land C");
+    }
+}
@@ -1,6 +1,10 @@
 pub mod db;
 pub mod derivation;
+pub mod dot;
+pub mod latex;
+pub mod normalize;
 pub mod parser;
 pub mod rule;
+pub mod session;
 
 pub use parser::LogicParser;
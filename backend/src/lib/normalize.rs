@@ -1,10 +1,12 @@
-use std::collections::BTreeMap;
-
-use crate::error::{BackendError, BackendResult};
-
-use super::models::formula_models::Identifier;
-
+use std::collections::{BTreeMap, BTreeSet};
 
+use crate::{
+    api::models::{Pair, SideCondition},
+    lib::derivation::{
+        formula::{Formula, Identifier, Term},
+        statement::Statement,
+    },
+};
 
 static ASCII_LOWER: [char; 26] = [
     'x', 'y', 'z', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p',
@@ -16,78 +18,191 @@ static ASCII_UPPER: [char; 26] = [
     'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
 ];
 
+static PREDICATES: [char; 26] = [
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k',
+    'l', 'm', 'n', 'o', 'x', 'y', 'z',
+];
+
+static FUNCTIONS: [char; 26] = [
+    'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'a',
+    'b', 'c', 'd', 'e', 'x', 'y', 'z',
+];
+
+#[derive(Default)]
+struct Names {
+    mapping: BTreeMap<String, String>,
+    used: BTreeSet<String>,
+}
+
+impl Names {
+    fn rename(&mut self, name: &str, pool: &[char], keep: bool) -> String {
+        if let Some(renamed) = self.mapping.get(name) {
+            return renamed.clone();
+        }
+        let renamed = if keep && !self.used.contains(name) {
+            name.to_string()
+        } else {
+            // Once the pool is used up, its names are reused with an index: A, ..., Z, A1, ...
+            (0..)
+                .flat_map(|i: usize| {
+                    pool.iter().map(move |c| match i {
+                        0 => c.to_string(),
+                        i => format!("{c}{i}"),
+                    })
+                })
+                .find(|c| !self.used.contains(c))
+                .unwrap_or_default()
+        };
+        self.used.insert(renamed.clone());
+        self.mapping.insert(name.to_string(), renamed.clone());
+        renamed
+    }
+
+    fn inverse(&self) -> Self {
+        let mapping = self
+            .mapping
+            .iter()
+            .map(|(from, to)| (to.clone(), from.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let used = mapping.values().cloned().collect();
+        Self { mapping, used }
+    }
+}
+
+/// Consistently renames literals, elements and predicates in order of their first
+/// occurrence. As the renaming is a bijection, everything derivable for a statement is
+/// derivable for its normal form as well.
+#[derive(Default)]
 pub struct Normalizer {
-    literal: BTreeMap<String, Identifier>,
-    element: BTreeMap<String, Identifier>,
-    capture_element: BTreeMap<String, Identifier>,
-    predicate: BTreeMap<String, Identifier>,
-    literal_counter: u32,
-    element_counter: u32,
-    predicate_counter: u32,
+    literal: Names,
+    element: Names,
+    predicate: Names,
+    function: Names,
+    // Names the renaming does not know are kept instead of being replaced.
+    keep_unknown: bool,
 }
 
 impl Normalizer {
-    pub fn next_literal(&mut self) -> BackendResult<Identifier> {
-        let letter = ASCII_UPPER[self.literal_counter as usize];
-        self.literal_counter += 1;
-        if self.literal_counter >= 26 {
-            return Err(BackendError::Unknown(
-                "Too many different literals in the formula".to_string(),
-            ));
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The renaming back to the original names. Names introduced later, e.g. by a
+    /// rule application, are kept unless they clash with an original name.
+    pub fn inverse(&self) -> Self {
+        Self {
+            literal: self.literal.inverse(),
+            element: self.element.inverse(),
+            predicate: self.predicate.inverse(),
+            function: self.function.inverse(),
+            keep_unknown: true,
         }
-        Ok(Identifier::Literal(letter.to_string()))
     }
 
-    pub fn next_element(&mut self) -> BackendResult<Identifier> {
-        let letter = ASCII_LOWER[self.element_counter as usize];
-        self.element_counter += 1;
-        if self.element_counter >= 18 {
-            return Err(BackendError::Unknown(
-                "Too many different elements in the formula".to_string(),
-            ));
+    fn identifier(&mut self, identifier: &Identifier) -> Identifier {
+        match identifier {
+            Identifier::Literal(s) => {
+                Identifier::Literal(self.literal.rename(s, &ASCII_UPPER, self.keep_unknown))
+            }
+            Identifier::Element(s) => {
+                Identifier::Element(self.element.rename(s, &ASCII_LOWER, self.keep_unknown))
+            }
         }
-        Ok(Identifier::Element(letter.to_string()))
     }
 
-    pub fn next_predicate(&mut self) -> BackendResult<Identifier> {
-        let letter = ASCII_LOWER[self.predicate_counter as usize];
-        self.predicate_counter += 1;
-        if self.predicate_counter >= 26 {
-            return Err(BackendError::Unknown(
-                "Too many different predicates in the formula".to_string(),
-            ));
+    fn term(&mut self, term: &Term) -> Term {
+        match term {
+            Term::Element(s) => {
+                Term::Element(self.element.rename(s, &ASCII_LOWER, self.keep_unknown))
+            }
+            Term::Function { name, args } => Term::Function {
+                name: self.function.rename(name, &FUNCTIONS, self.keep_unknown),
+                args: args.iter().map(|arg| self.term(arg)).collect(),
+            },
         }
-        Ok(Identifier::Element(letter.to_string()))
     }
-}
 
-pub fn normalize(formula: Formula, mapping: &mut Normalizer) -> BackendResult<Formula> {
-    match formula {
-        Formula::And { lhs, rhs } => Ok(Formula::And {
-            lhs: Box::new(normalize(*lhs, mapping)?),
-            rhs: Box::new(normalize(*rhs, mapping)?),
-        }),
-        Formula::Or { lhs, rhs } => Ok(Formula::Or {
-            lhs: Box::new(normalize(*lhs, mapping)?),
-            rhs: Box::new(normalize(*rhs, mapping)?),
-        }),
-        Formula::Imp { lhs, rhs } => Ok(Formula::Imp {
-            lhs: Box::new(normalize(*lhs, mapping)?),
-            rhs: Box::new(normalize(*rhs, mapping)?),
-        }),
-        Formula::Not(formula) => Ok(Formula::Not(Box::new(normalize(*formula, mapping)?))),
-        Formula::True => Ok(Formula::True),
-        Formula::False => Ok(Formula::False),
-        Formula::Forall { .. } => todo!(),
-        Formula::Exists { .. } => todo!(),
-        Formula::Predicate { .. } => todo!(),
-        Formula::Ident(Identifier::Literal(s)) => {
-            if mapping.literal.contains_key(&s) {
-                Ok(Formula::Ident(mapping.literal.get(&s).unwrap().clone()))
-            } else {
-                Ok(Formula::Ident(mapping.next_literal()?))
+    pub fn formula(&mut self, formula: &Formula) -> Formula {
+        match formula {
+            Formula::And { lhs, rhs } => Formula::And {
+                lhs: Box::new(self.formula(lhs)),
+                rhs: Box::new(self.formula(rhs)),
+            },
+            Formula::Or { lhs, rhs } => Formula::Or {
+                lhs: Box::new(self.formula(lhs)),
+                rhs: Box::new(self.formula(rhs)),
+            },
+            Formula::Imp { lhs, rhs } => Formula::Imp {
+                lhs: Box::new(self.formula(lhs)),
+                rhs: Box::new(self.formula(rhs)),
+            },
+            Formula::Not(formula) => Formula::Not(Box::new(self.formula(formula))),
+            Formula::True => Formula::True,
+            Formula::False => Formula::False,
+            Formula::Ident(identifier) => Formula::Ident(self.identifier(identifier)),
+            Formula::Forall {
+                identifier,
+                formula,
+            } => Formula::Forall {
+                identifier: self.identifier(identifier),
+                formula: Box::new(self.formula(formula)),
+            },
+            Formula::Exists {
+                identifier,
+                formula,
+            } => Formula::Exists {
+                identifier: self.identifier(identifier),
+                formula: Box::new(self.formula(formula)),
+            },
+            Formula::Predicate {
+                identifier,
+                identifiers,
+            } => {
+                let name = match identifier {
+                    Identifier::Element(s) | Identifier::Literal(s) => s,
+                };
+                let name = self.predicate.rename(name, &PREDICATES, self.keep_unknown);
+                Formula::Predicate {
+                    identifier: Identifier::Element(name),
+                    identifiers: identifiers.iter().map(|t| self.term(t)).collect(),
+                }
             }
+            Formula::Eq { lhs, rhs } => Formula::Eq {
+                lhs: self.term(lhs),
+                rhs: self.term(rhs),
+            },
         }
-        _ => panic!("This should never happen"),
+    }
+
+    pub fn statement(&mut self, statement: &Statement) -> Statement {
+        let lhs = statement.lhs.iter().map(|f| self.formula(f)).collect();
+        let formula = self.formula(&statement.formula);
+        let mut sidecondition = statement
+            .sidecondition
+            .iter()
+            .map(|sc| match sc {
+                SideCondition::NotFree(pair) => SideCondition::NotFree(Pair {
+                    element: self.identifier(&pair.element),
+                    placeholder: self.identifier(&pair.placeholder),
+                }),
+            })
+            .collect::<Vec<_>>();
+        sidecondition.sort();
+        sidecondition.dedup();
+        Statement {
+            lhs,
+            formula,
+            sidecondition,
+        }
+    }
+}
+
+impl Statement {
+    /// The canonical representative of all statements equal up to renaming, together
+    /// with the renaming that produced it.
+    pub fn normalize(&self) -> (Statement, Normalizer) {
+        let mut normalizer = Normalizer::new();
+        let statement = normalizer.statement(self);
+        (statement, normalizer)
     }
 }
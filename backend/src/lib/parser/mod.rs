@@ -1,253 +1,349 @@
-use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Display;
 
-use log::info;
-use pest::iterators::Pair;
-use pest::Parser;
+use pest::error::{Error, ErrorVariant, InputLocation, LineColLocation};
+use pest::iterators::{Pair, Pairs};
+use pest::pratt_parser::{Assoc, Op, PrattParser};
+use pest::{Parser, Position, Span};
 use pest_derive::Parser;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use crate::lib::derivation::formula::{Formula, Identifier};
+use crate::lib::derivation::formula::{Formula, Identifier, Term};
 
 #[derive(Parser)]
 #[grammar = "./lib/parser/grammar.pest"] // relative to src
 pub struct LogicParser {}
 
-impl LogicParser {
-    pub fn parse_input(input: &str) -> Result<Formula, String> {
-        let pairs = LogicParser::parse(Rule::main, input);
-        let mut pairs = match pairs {
-            Err(e) => return Err(e.to_string()),
-            Ok(p) => p,
+type ParseResult<T> = Result<T, Box<Error<Rule>>>;
+
+/// Where and why parsing a formula failed. Offsets are in bytes, lines and columns
+/// start at 1.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    /// The input at the error location, empty at the end of the input.
+    pub token: String,
+    /// The constructs that would have been accepted at the error location.
+    pub expected: Vec<String>,
+    pub unexpected: Vec<String>,
+    /// The human readable error including a pointer into the input.
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Rule {
+    fn describe(&self) -> &'static str {
+        match self {
+            Rule::lit => "literal",
+            Rule::elem | Rule::var | Rule::list_elem => "identifier",
+            Rule::list => "list of variables",
+            Rule::predicate => "predicate",
+            Rule::function => "function",
+            Rule::eq => "equality",
+            Rule::true_ => "true",
+            Rule::false_ => "false",
+            Rule::forall | Rule::forall_ => "forall_x",
+            Rule::exists | Rule::exists_ => "exists_x",
+            Rule::quantifier | Rule::quantified => "quantifier",
+            Rule::and => "and",
+            Rule::or => "or",
+            Rule::not => "not",
+            Rule::implication => "->",
+            Rule::expr => "formula",
+            Rule::EOI => "end of input",
+            _ => "formula",
+        }
+    }
+}
+
+fn describe_all(rules: &[Rule]) -> Vec<String> {
+    rules
+        .iter()
+        .map(|r| r.describe().to_string())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+// The identifier or symbol starting at the given offset.
+fn token_at(input: &str, offset: usize) -> String {
+    let rest = input.get(offset..).unwrap_or_default();
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '\'';
+    match rest.chars().next() {
+        None => String::new(),
+        Some(c) if is_ident(c) => rest.chars().take_while(|c| is_ident(*c)).collect(),
+        Some(c) => c.to_string(),
+    }
+}
+
+impl ParseError {
+    fn from_pest(input: &str, error: Error<Rule>) -> Self {
+        let (offset, end) = match error.location {
+            InputLocation::Pos(pos) => (pos, pos),
+            InputLocation::Span(span) => span,
         };
-        let first = pairs.next().unwrap();
-        let captures = BTreeMap::new();
-        let (_, f) = Self::parse_formula(0, first, &captures)?;
-        Ok(f)
-    }
-
-    fn parse_formula(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
-        info!("parse formula");
-        match pair.as_rule() {
-            Rule::var => Self::parse_var(num, pair, captures),
-            Rule::and => Self::parse_and(num, pair, captures),
-            Rule::or => Self::parse_or(num, pair, captures),
-            Rule::not => Self::parse_not(num, pair, captures),
-            Rule::implication => Self::parse_impl(num, pair, captures),
-            Rule::predicate => Self::parse_predicate(num, pair, captures),
-            Rule::quantifier => Self::parse_quantifier(num, pair, captures),
-            Rule::lit => Self::parse_literal(num, pair, captures),
-            Rule::true_ => Self::parse_true(num, pair, captures),
-            Rule::false_ => Self::parse_false(num, pair, captures),
-            e => panic!("Unexpected rule: {:?}", e),
+        let (line, column) = match error.line_col {
+            LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+        };
+        let token = token_at(input, offset);
+        let (expected, unexpected) = match &error.variant {
+            ErrorVariant::ParsingError {
+                positives,
+                negatives,
+            } => (describe_all(positives), describe_all(negatives)),
+            ErrorVariant::CustomError { .. } => (vec![], vec![]),
+        };
+        Self {
+            offset,
+            end: end.max(offset + token.len()),
+            line,
+            column,
+            token,
+            expected,
+            unexpected,
+            message: error
+                .renamed_rules(|r| r.describe().to_string())
+                .to_string(),
         }
     }
+}
+
+fn custom_error(span: Span, message: &str) -> Box<Error<Rule>> {
+    Box::new(Error::new_from_span(
+        ErrorVariant::CustomError {
+            message: message.to_string(),
+        },
+        span,
+    ))
+}
+
+impl LogicParser {
+    pub fn parse_input(input: &str) -> Result<Formula, Box<ParseError>> {
+        Self::parse_main(input).map_err(|e| Box::new(ParseError::from_pest(input, *e)))
+    }
 
-    fn parse_true(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
-        Ok((num, Formula::True))
-    }
-
-    fn parse_false(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
-        Ok((num, Formula::False))
-    }
-
-    fn parse_literal(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
-        let name = pair.as_str();
-        if let Some(capture_name) = captures.get(name) {
-            Ok((
-                num,
-                Formula::Ident(Identifier::Literal(capture_name.to_string())),
+    fn parse_main(input: &str) -> ParseResult<Formula> {
+        let mut pairs = LogicParser::parse(Rule::main, input)?;
+        let first = pairs.next().ok_or_else(|| {
+            Box::new(Error::new_from_pos(
+                ErrorVariant::ParsingError {
+                    positives: vec![Rule::expr],
+                    negatives: vec![],
+                },
+                Position::from_start(input),
             ))
-        } else {
-            Ok((num, Formula::Ident(Identifier::Literal(name.to_string()))))
+        })?;
+        Self::parse_expr(first.into_inner(), &Self::pratt())
+    }
+
+    /// Operators from the loosest to the tightest binding: `->` (right associative),
+    /// `or`, `and` and finally `not`. The quantifiers `forall_x` and `exists_x` are not
+    /// operators, their body extends as far to the right as possible.
+    fn pratt() -> PrattParser<Rule> {
+        PrattParser::new()
+            .op(Op::infix(Rule::implication, Assoc::Right))
+            .op(Op::infix(Rule::or, Assoc::Left))
+            .op(Op::infix(Rule::and, Assoc::Left))
+            .op(Op::prefix(Rule::not))
+    }
+
+    fn parse_expr(pairs: Pairs<Rule>, pratt: &PrattParser<Rule>) -> ParseResult<Formula> {
+        pratt
+            .map_primary(|pair| Self::parse_formula(pair, pratt))
+            .map_prefix(|op, formula| Self::parse_prefix(op, formula?))
+            .map_infix(|lhs, op, rhs| Self::parse_infix(lhs?, op, rhs?))
+            .parse(pairs)
+    }
+
+    fn parse_formula(pair: Pair<Rule>, pratt: &PrattParser<Rule>) -> ParseResult<Formula> {
+        match pair.as_rule() {
+            Rule::expr => Self::parse_expr(pair.into_inner(), pratt),
+            Rule::quantified => Self::parse_quantified(pair, pratt),
+            Rule::predicate => Self::parse_predicate(pair),
+            Rule::eq => Self::parse_eq(pair),
+            Rule::lit => Ok(Formula::Ident(Identifier::Literal(
+                pair.as_str().to_string(),
+            ))),
+            Rule::true_ => Ok(Formula::True),
+            Rule::false_ => Ok(Formula::False),
+            e => Err(custom_error(
+                pair.as_span(),
+                &format!("Unexpected rule: {:?}", e),
+            )),
         }
     }
 
-    fn parse_var(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
-        info!("parse var {:?}", pair);
-        let name = pair.as_str();
-        if let Some(capture_name) = captures.get(name) {
-            Ok((
-                num,
-                Formula::Ident(Identifier::Literal(capture_name.to_string())),
-            ))
-        } else {
-            Ok((num, Formula::Ident(Identifier::Literal(name.to_string()))))
+    fn parse_prefix(op: Pair<Rule>, formula: Formula) -> ParseResult<Formula> {
+        match op.as_rule() {
+            Rule::not => Ok(Formula::Not(Box::new(formula))),
+            e => Err(custom_error(
+                op.as_span(),
+                &format!("Unexpected prefix operator: {:?}", e),
+            )),
         }
     }
 
-    fn parse_and(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
+    fn parse_quantified(pair: Pair<Rule>, pratt: &PrattParser<Rule>) -> ParseResult<Formula> {
+        let span = pair.as_span();
         let mut pairs = pair.into_inner();
-        let (num, f_lhs) =
-            LogicParser::parse_formula(num, pairs.next().unwrap(), captures).unwrap();
-        let (num, r_hs) = LogicParser::parse_formula(num, pairs.next().unwrap(), captures).unwrap();
-        Ok((
-            num,
-            Formula::And {
-                lhs: Box::new(f_lhs),
-                rhs: Box::new(r_hs),
-            },
-        ))
-    }
-
-    fn parse_or(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
-        let mut pairs = pair.into_inner();
-        let (num, f_lhs) =
-            LogicParser::parse_formula(num, pairs.next().unwrap(), captures).unwrap();
-        let (num, f_rhs) =
-            LogicParser::parse_formula(num, pairs.next().unwrap(), captures).unwrap();
-        Ok((
-            num,
-            Formula::Or {
-                lhs: Box::new(f_lhs),
-                rhs: Box::new(f_rhs),
-            },
-        ))
-    }
-
-    fn parse_not(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
-        let (num, f) =
-            LogicParser::parse_formula(num, pair.into_inner().next().unwrap(), captures).unwrap();
-        Ok((num, Formula::Not(Box::new(f))))
-    }
-
-    fn parse_quantifier(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
-        let pair = pair.into_inner().next().unwrap();
+        let quantifier = pairs
+            .next()
+            .and_then(|quantifier| quantifier.into_inner().next())
+            .ok_or_else(|| custom_error(span, "Missing quantifier"))?;
+        let formula = pairs
+            .next()
+            .ok_or_else(|| custom_error(span, "Missing quantified formula"))?;
+        let formula = Box::new(Self::parse_formula(formula, pratt)?);
+
+        let rule = quantifier.as_rule();
+        let name = quantifier
+            .into_inner()
+            .find(|p| p.as_rule() == Rule::elem)
+            .ok_or_else(|| custom_error(span, "Missing quantified variable"))?
+            .as_str()
+            .to_string();
+        match rule {
+            Rule::forall => Ok(Formula::Forall {
+                identifier: Identifier::Element(name),
+                formula,
+            }),
+            Rule::exists => Ok(Formula::Exists {
+                identifier: Identifier::Element(name),
+                formula,
+            }),
+            e => Err(custom_error(
+                span,
+                &format!("Unexpected quantifier: {:?}", e),
+            )),
+        }
+    }
+
+    fn parse_infix(lhs: Formula, op: Pair<Rule>, rhs: Formula) -> ParseResult<Formula> {
+        let lhs = Box::new(lhs);
+        let rhs = Box::new(rhs);
+        match op.as_rule() {
+            Rule::and => Ok(Formula::And { lhs, rhs }),
+            Rule::or => Ok(Formula::Or { lhs, rhs }),
+            Rule::implication => Ok(Formula::Imp { lhs, rhs }),
+            e => Err(custom_error(
+                op.as_span(),
+                &format!("Unexpected operator: {:?}", e),
+            )),
+        }
+    }
+
+    fn parse_term(pair: Pair<Rule>) -> ParseResult<Term> {
+        let span = pair.as_span();
         match pair.as_rule() {
-            Rule::forall => Self::parse_forall(num, pair, captures),
-            Rule::exists => Self::parse_exists(num, pair, captures),
-            _ => panic!("Unexpected quantifier: {}", pair),
+            Rule::var => Ok(Term::Element(pair.as_str().to_string())),
+            Rule::function => {
+                let (name, args) = Self::parse_application(pair)?;
+                Ok(Term::Function { name, args })
+            }
+            e => Err(custom_error(span, &format!("Unexpected term: {:?}", e))),
         }
     }
 
-    fn parse_impl(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
-        let mut pairs = pair.into_inner();
-        let (num, f_lhs) =
-            LogicParser::parse_formula(num, pairs.next().unwrap(), captures).unwrap();
-        let (num, f_rhs) =
-            LogicParser::parse_formula(num, pairs.next().unwrap(), captures).unwrap();
-        Ok((
-            num,
-            Formula::Imp {
-                lhs: Box::new(f_lhs),
-                rhs: Box::new(f_rhs),
-            },
-        ))
-    }
-
-    fn parse_forall(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
+    // The name and the arguments of a predicate or function application.
+    fn parse_application(pair: Pair<Rule>) -> ParseResult<(String, Vec<Term>)> {
+        let span = pair.as_span();
         let mut pairs = pair.into_inner();
-        let _ = pairs.next().unwrap();
-        let var = pairs.next().unwrap();
-        info!("{:?}", var);
-        let name = var.as_str();
-        // let capure_name = format!("{}{}", name, num);
-        let capure_name = name.to_string();
-
-        let mut captures = captures.clone();
-        captures.insert(name, capure_name.clone());
-        let (num, f) =
-            LogicParser::parse_formula(num + 1, pairs.next().unwrap(), &captures).unwrap();
-        Ok((
-            num,
-            Formula::Forall {
-                identifier: Identifier::Element(capure_name.to_string()),
-                formula: Box::new(f),
-            },
-        ))
-    }
-
-    fn parse_exists(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
-        let mut pairs = pair.into_inner();
-        let _ = pairs.next().unwrap();
-        let var = pairs.next().unwrap();
-        let name = var.as_str();
-        // let capure_name = format!("{}{}", name, num);
-        let capure_name = name.to_string();
-
-        let mut captures = captures.clone();
-        captures.insert(name, capure_name.clone());
-        let (num, f) =
-            LogicParser::parse_formula(num + 1, pairs.next().unwrap(), &captures).unwrap();
-        Ok((
-            num,
-            Formula::Exists {
-                identifier: Identifier::Element(capure_name.to_string()),
-                formula: Box::new(f),
-            },
-        ))
-    }
-
-    fn parse_predicate(
-        num: u32,
-        pair: Pair<Rule>,
-        captures: &BTreeMap<&str, String>,
-    ) -> Result<(u32, Formula), String> {
+        let name = pairs
+            .next()
+            .ok_or_else(|| custom_error(span, "Missing name"))?
+            .as_str()
+            .to_string();
+        let args = pairs
+            .next()
+            .ok_or_else(|| custom_error(span, "Missing arguments"))?
+            .into_inner()
+            .map(Self::parse_term)
+            .collect::<ParseResult<Vec<_>>>()?;
+        Ok((name, args))
+    }
+
+    fn parse_predicate(pair: Pair<Rule>) -> ParseResult<Formula> {
+        let (name, args) = Self::parse_application(pair)?;
+        Ok(Formula::Predicate {
+            identifier: Identifier::Element(name),
+            identifiers: args,
+        })
+    }
+
+    fn parse_eq(pair: Pair<Rule>) -> ParseResult<Formula> {
+        let span = pair.as_span();
         let mut pairs = pair.into_inner();
-        let name = pairs.next().unwrap().as_str().to_string();
-        info!("name: {}", name);
-        let mut args = Vec::new();
-        let arguments = pairs.next().unwrap().into_inner();
-        for arg in arguments {
-            let name = arg.as_str();
-            if let Some(capture_name) = captures.get(name) {
-                args.push(Identifier::Element(capture_name.to_string()));
-            } else {
-                args.push(Identifier::Element(name.to_string()));
-            }
+        let mut term = || {
+            pairs
+                .next()
+                .ok_or_else(|| custom_error(span, "Missing term"))
+                .and_then(Self::parse_term)
+        };
+        let lhs = term()?;
+        let rhs = term()?;
+        Ok(Formula::Eq { lhs, rhs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Formula {
+        LogicParser::parse_input(input).unwrap()
+    }
+
+    #[test]
+    fn quantifiers_extend_to_the_right() {
+        let formula = parse("forall_x p(x) -> q(x)");
+        let Formula::Forall { formula, .. } = formula else {
+            panic!("expected a quantifier, got {formula}");
+        };
+        assert_eq!(*formula, parse("p(x) -> q(x)"));
+
+        assert_eq!(
+            parse("A and exists_x p(x) or B"),
+            parse("A and (exists_x (p(x) or B))")
+        );
+        assert_eq!(
+            parse("not forall_x p(x) and A"),
+            parse("not (forall_x (p(x) and A))")
+        );
+        assert_ne!(
+            parse("(forall_x p(x)) -> q(x)"),
+            parse("forall_x (p(x) -> q(x))")
+        );
+    }
+
+    #[test]
+    fn operators_bind_by_precedence() {
+        assert_eq!(parse("A and B or C"), parse("(A and B) or C"));
+        assert_eq!(parse("A -> B -> C"), parse("A -> (B -> C)"));
+        assert_eq!(parse("not A and B"), parse("(not A) and B"));
+    }
+
+    #[test]
+    fn printed_formulas_parse_again() {
+        for input in [
+            "forall_x p(x) -> q(x)",
+            "(forall_x p(x)) -> q(x)",
+            "A and (exists_x p(x)) and B",
+            "not (forall_x p(x)) or exists_y p(y)",
+            "(A -> B) -> not (C or D) and E",
+            "forall_x exists_y f(x) = y",
+        ] {
+            let formula = parse(input);
+            assert_eq!(formula.to_string(), input);
+            assert_eq!(parse(&formula.to_string()), formula);
         }
-        Ok((
-            num,
-            Formula::Predicate {
-                identifier: Identifier::Element(name),
-                identifiers: args,
-            },
-        ))
     }
 }
@@ -1,10 +1,10 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::BTreeMap;
 
 use log::info;
 
 use crate::{
     error::{BackendError, BackendResult},
-    lib::derivation::formula::{Formula, Identifier},
+    lib::derivation::formula::{Formula, Identifier, Term},
 };
 
 use super::{RuleFormula, RuleIdentifier};
@@ -13,12 +13,16 @@ pub fn get_formula(
     i: &RuleIdentifier,
     mapping: &BTreeMap<RuleIdentifier, Formula>,
 ) -> BackendResult<Formula> {
+    if let RuleIdentifier::Negation(_) = i {
+        let f = get_formula(&i.base(), mapping)?;
+        return Ok(Formula::Not(Box::new(f)));
+    }
     match mapping.get(i) {
         Some(f) => Ok(f.clone()),
         None => {
             let name = match i {
                 RuleIdentifier::Element(s) => s.to_string(),
-                RuleIdentifier::Formula(s) => s.to_string(),
+                RuleIdentifier::Formula(s) | RuleIdentifier::Negation(s) => s.to_string(),
             };
             Err(BackendError::IdNotFound {
                 entity: "Identifier".to_string(),
@@ -111,13 +115,27 @@ impl RuleFormula {
                 let from = substitution.get(lhs);
                 let to = substitution.get(rhs);
                 if let (Some(from), Some(to)) = (from, to) {
-                    f.apply_substitution(from, to, BTreeSet::new())
+                    f.apply_substitution(from, to)
                 } else {
                     Err(BackendError::BadRequest(
                         "The substitution must be a valid substitution".to_string(),
                     ))
                 }
             }
+            RuleFormula::Eq { lhs, rhs } => {
+                let lhs = substitution.get(lhs);
+                let rhs = substitution.get(rhs);
+                if let (Some(lhs), Some(rhs)) = (lhs, rhs) {
+                    Ok(Formula::Eq {
+                        lhs: Term::Element(lhs.to_string()),
+                        rhs: Term::Element(rhs.to_string()),
+                    })
+                } else {
+                    Err(BackendError::BadRequest(
+                        "The terms of the equality must be given".to_string(),
+                    ))
+                }
+            }
         }
     }
 }
@@ -27,6 +27,11 @@ pub enum Rules {
     ExistsIntro,
     AlphaExists,
     AlphaForall,
+    EqIntro,
+    EqElim,
+    Raa,
+    Lem,
+    DoubleNegElim,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema, PartialOrd, PartialEq, Ord, Eq)]
@@ -34,6 +39,8 @@ pub enum Rules {
 pub enum RuleIdentifier {
     Formula(u32),
     Element(String),
+    /// The negation of the formula `Formula(id)` stands for, e.g. `¬A` in `A ∨ ¬A`.
+    Negation(u32),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -69,6 +76,10 @@ pub enum RuleFormula {
         lhs: RuleIdentifier,
         rhs: RuleIdentifier,
     },
+    Eq {
+        lhs: RuleIdentifier,
+        rhs: RuleIdentifier,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
@@ -84,6 +95,23 @@ pub struct DerivationRule {
     pub conclusion: RuleStatement,
 }
 
+impl Rules {
+    /// Rules that are only sound in classical logic.
+    pub fn is_classical(&self) -> bool {
+        matches!(self, Rules::Raa | Rules::Lem | Rules::DoubleNegElim)
+    }
+}
+
+impl RuleIdentifier {
+    /// The identifier that is mapped to a formula, `A` for both `A` and `¬A`.
+    pub fn base(&self) -> RuleIdentifier {
+        match self {
+            RuleIdentifier::Negation(id) => RuleIdentifier::Formula(*id),
+            i => i.clone(),
+        }
+    }
+}
+
 impl RuleFormula {
     pub fn identifiers(&self) -> BTreeSet<RuleIdentifier> {
         let mut ident = BTreeSet::new();
@@ -93,7 +121,8 @@ impl RuleFormula {
             }
             RuleFormula::And { lhs, rhs }
             | RuleFormula::Or { lhs, rhs }
-            | RuleFormula::Imp { lhs, rhs } => {
+            | RuleFormula::Imp { lhs, rhs }
+            | RuleFormula::Eq { lhs, rhs } => {
                 ident.insert(lhs.clone());
                 ident.insert(rhs.clone());
             }
@@ -122,7 +151,7 @@ impl RuleFormula {
                 ident.insert(rhs.clone());
             }
         }
-        ident
+        ident.into_iter().map(|i| i.base()).collect()
     }
 }
 
@@ -130,7 +159,7 @@ impl RuleStatement {
     pub fn identifiers(&self) -> BTreeSet<RuleIdentifier> {
         let mut set = BTreeSet::new();
         if let Some(lhs) = &self.lhs {
-            set.insert(lhs.clone());
+            set.insert(lhs.base());
         }
         set.extend(self.formula.identifiers());
         set
@@ -161,6 +190,9 @@ impl Display for RuleIdentifier {
             RuleIdentifier::Element(id) => {
                 write!(f, "{}", id)
             }
+            RuleIdentifier::Negation(id) => {
+                write!(f, "¬{}", RuleIdentifier::Formula(*id))
+            }
         }
     }
 }
@@ -208,6 +240,9 @@ impl Display for RuleFormula {
             } => {
                 write!(f, "{}[{} := {}]", identifier, lhs, rhs)
             }
+            RuleFormula::Eq { lhs, rhs } => {
+                write!(f, "{} = {}", lhs, rhs)
+            }
         }
     }
 }
@@ -1,7 +1,7 @@
 use super::{DerivationRule, RuleFormula, RuleIdentifier, RuleStatement, Rules};
 
 impl Rules {
-    pub fn all_rules() -> [DerivationRule; 18] {
+    pub fn all_rules() -> [DerivationRule; 23] {
         let ax = DerivationRule {
             name: Rules::Ax,
             premises: vec![],
@@ -312,6 +312,83 @@ impl Rules {
             },
         };
 
+        let eq_intro = DerivationRule {
+            name: Rules::EqIntro,
+            premises: vec![],
+            conclusion: RuleStatement {
+                lhs: None,
+                formula: RuleFormula::Eq {
+                    lhs: RuleIdentifier::Element("t".to_string()),
+                    rhs: RuleIdentifier::Element("t".to_string()),
+                },
+            },
+        };
+
+        let eq_elim = DerivationRule {
+            name: Rules::EqElim,
+            premises: vec![
+                RuleStatement {
+                    lhs: None,
+                    formula: RuleFormula::Eq {
+                        lhs: RuleIdentifier::Element("s".to_string()),
+                        rhs: RuleIdentifier::Element("t".to_string()),
+                    },
+                },
+                RuleStatement {
+                    lhs: None,
+                    formula: RuleFormula::Substitution {
+                        identifier: RuleIdentifier::Formula(0),
+                        lhs: RuleIdentifier::Element("x".to_string()),
+                        rhs: RuleIdentifier::Element("s".to_string()),
+                    },
+                },
+            ],
+            conclusion: RuleStatement {
+                lhs: None,
+                formula: RuleFormula::Substitution {
+                    identifier: RuleIdentifier::Formula(0),
+                    lhs: RuleIdentifier::Element("x".to_string()),
+                    rhs: RuleIdentifier::Element("t".to_string()),
+                },
+            },
+        };
+
+        let raa = DerivationRule {
+            name: Rules::Raa,
+            premises: vec![RuleStatement {
+                lhs: Some(RuleIdentifier::Negation(0)),
+                formula: RuleFormula::False,
+            }],
+            conclusion: RuleStatement {
+                lhs: None,
+                formula: RuleFormula::Ident(RuleIdentifier::Formula(0)),
+            },
+        };
+
+        let lem = DerivationRule {
+            name: Rules::Lem,
+            premises: vec![],
+            conclusion: RuleStatement {
+                lhs: None,
+                formula: RuleFormula::Or {
+                    lhs: RuleIdentifier::Formula(0),
+                    rhs: RuleIdentifier::Negation(0),
+                },
+            },
+        };
+
+        let double_neg_elim = DerivationRule {
+            name: Rules::DoubleNegElim,
+            premises: vec![RuleStatement {
+                lhs: None,
+                formula: RuleFormula::Not(RuleIdentifier::Negation(0)),
+            }],
+            conclusion: RuleStatement {
+                lhs: None,
+                formula: RuleFormula::Ident(RuleIdentifier::Formula(0)),
+            },
+        };
+
         [
             ax,
             impl_intro,
@@ -331,6 +408,11 @@ impl Rules {
             exists_elim,
             alpha_forall,
             alpha_exists,
+            eq_intro,
+            eq_elim,
+            raa,
+            lem,
+            double_neg_elim,
         ]
     }
     pub fn get_rule(&self) -> DerivationRule {
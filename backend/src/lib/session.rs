@@ -0,0 +1,165 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    api::models::{CreateTreeRequest, Node},
+    error::{BackendError, BackendResult},
+    lib::{
+        derivation::{formula::Formula, statement::Statement},
+        rule::{RuleIdentifier, Rules},
+    },
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct Goal {
+    pub name: Uuid,
+    pub statement: Statement,
+}
+
+/// A proof built step by step. Every goal has an id, a goal is closed once a rule was
+/// applied to it and the applied rules form the derivation tree.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProofState {
+    pub root: Uuid,
+    pub classical: bool,
+    pub statements: BTreeMap<Uuid, Statement>,
+    pub steps: Vec<Node>,
+    /// Undone steps, the last one is redone first.
+    pub undone: Vec<Node>,
+}
+
+impl ProofState {
+    pub fn new(statement: Statement, classical: bool) -> Self {
+        let root = Uuid::new_v4();
+        Self {
+            root,
+            classical,
+            statements: BTreeMap::from([(root, statement)]),
+            steps: Vec::new(),
+            undone: Vec::new(),
+        }
+    }
+
+    fn step(&self, goal: &Uuid) -> Option<&Node> {
+        self.steps.iter().find(|node| node.name == *goal)
+    }
+
+    /// The goals without an applied rule, from left to right.
+    pub fn open_goals(&self) -> Vec<Goal> {
+        let mut goals = Vec::new();
+        let mut pending = vec![self.root];
+        while let Some(name) = pending.pop() {
+            match self.step(&name) {
+                Some(node) => pending.extend(node.premisses.iter().rev()),
+                None => {
+                    if let Some(statement) = self.statements.get(&name) {
+                        goals.push(Goal {
+                            name,
+                            statement: statement.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        goals
+    }
+
+    /// Applies the rule to an open goal and returns the new goals.
+    pub fn apply(
+        &mut self,
+        goal: Uuid,
+        rule: Rules,
+        mapping: &mut BTreeMap<RuleIdentifier, Formula>,
+        substitution: &mut BTreeMap<RuleIdentifier, String>,
+    ) -> BackendResult<Vec<Goal>> {
+        if rule.is_classical() && !self.classical {
+            return Err(BackendError::BadRequest(format!(
+                "{:?} is a classical rule and not allowed in this exercise",
+                rule
+            )));
+        }
+        let statement = self
+            .open_goals()
+            .into_iter()
+            .find(|open| open.name == goal)
+            .ok_or_else(|| BackendError::BadRequest(format!("{goal} is not an open goal")))?
+            .statement;
+        let rule_definition = rule.get_rule();
+        let unbound = statement.infer_rule_mapping(&rule_definition, mapping, substitution)?;
+        if !unbound.is_empty() {
+            return Err(BackendError::BadRequest(format!(
+                "The mapping of {} is missing",
+                unbound
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+        let premisses = statement.apply_rule(rule_definition, mapping, substitution)?;
+
+        let goals = premisses
+            .into_iter()
+            .map(|statement| Goal {
+                name: Uuid::new_v4(),
+                statement,
+            })
+            .collect::<Vec<_>>();
+        for new in goals.iter() {
+            self.statements.insert(new.name, new.statement.clone());
+        }
+        self.steps.push(Node {
+            name: goal,
+            statement,
+            rule,
+            premisses: goals.iter().map(|g| g.name).collect(),
+        });
+        // A new step discards the undone ones.
+        self.undone.clear();
+        self.prune();
+        Ok(goals)
+    }
+
+    pub fn undo(&mut self) -> BackendResult<()> {
+        let step = self
+            .steps
+            .pop()
+            .ok_or_else(|| BackendError::BadRequest("There is nothing to undo".to_string()))?;
+        self.undone.push(step);
+        Ok(())
+    }
+
+    pub fn redo(&mut self) -> BackendResult<()> {
+        let step = self
+            .undone
+            .pop()
+            .ok_or_else(|| BackendError::BadRequest("There is nothing to redo".to_string()))?;
+        self.steps.push(step);
+        Ok(())
+    }
+
+    // Forgets the goals that neither the steps nor the undone steps refer to.
+    fn prune(&mut self) {
+        let mut used = BTreeSet::from([self.root]);
+        for node in self.steps.iter().chain(self.undone.iter()) {
+            used.extend(node.premisses.iter().cloned());
+        }
+        self.statements.retain(|name, _| used.contains(name));
+    }
+
+    pub fn tree(&self) -> CreateTreeRequest {
+        CreateTreeRequest {
+            nodes: self.steps.clone(),
+            root_id: self.root,
+        }
+    }
+
+    pub fn statement(&self) -> BackendResult<&Statement> {
+        self.statements
+            .get(&self.root)
+            .ok_or_else(|| BackendError::Unknown("The proof has no root".to_string()))
+    }
+}
@@ -13,7 +13,7 @@ use axum::{http, middleware, Router};
 use http::Method;
 use log::{error, info};
 use logging::mw_map_response;
-use sea_orm::DatabaseConnection;
+use sea_orm::{DatabaseConnection, EntityTrait, TransactionTrait};
 use tower_http::cors::{Any, CorsLayer};
 use utils::connect_db;
 use utoipa::OpenApi;
@@ -31,15 +31,98 @@ async fn handler_404(request: Request) -> impl IntoResponse {
     )
 }
 
+async fn run_command(
+    command: &str,
+    flags: &[String],
+    db: &DatabaseConnection,
+) -> error::BackendResult<()> {
+    let trx = db.begin().await?;
+    match command {
+        "repair-sideconditions" => {
+            let report = lib::db::maintenance::repair_sideconditions(&trx).await?;
+            info!("{report:?}");
+        }
+        "check-hints" => {
+            let params = api::models::HintGraphCheckParams {
+                delete_invalid: flags.iter().any(|flag| flag == "--delete-invalid"),
+                remove_unreachable: flags.iter().any(|flag| flag == "--remove-unreachable"),
+            };
+            let report = lib::db::maintenance::check_hint_graph(&trx, &params).await?;
+            for invalid in report.invalid.iter() {
+                info!(
+                    "invalid {:?} derivation of {}: {}",
+                    invalid.rule, invalid.statement, invalid.message
+                );
+            }
+            for cycle in report.cycles.iter() {
+                info!("cycle through {:?}", cycle);
+            }
+            info!(
+                "checked {} derivations: {} invalid, {} cycles, {} unreachable statements",
+                report.checked,
+                report.invalid.len(),
+                report.cycles.len(),
+                report.unreachable.len()
+            );
+        }
+        "dot-tree" => {
+            // The tree is read as the JSON of a `CreateTreeRequest`.
+            let tree = serde_json::from_reader::<_, api::models::CreateTreeRequest>(
+                std::io::stdin(),
+            )
+            .map_err(|e| error::BackendError::BadRequest(format!("invalid tree: {e}")))?;
+            println!("{}", lib::dot::tree_to_dot(tree.root_id, &tree.nodes)?);
+        }
+        "dot-hints" => {
+            let id = flags
+                .first()
+                .and_then(|id| id.parse::<uuid::Uuid>().ok())
+                .ok_or_else(|| {
+                    error::BackendError::BadRequest("expected an exercise id".to_string())
+                })?;
+            let exercise = db::exercise::Entity::find_by_id(id)
+                .one(&trx)
+                .await?
+                .ok_or_else(|| error::BackendError::IdNotFound {
+                    entity: "Exercise".to_string(),
+                    id: id.to_string(),
+                })?;
+            let graph = lib::db::graph::HintGraph::load(&trx, exercise.statement_id).await?;
+            println!("{}", lib::dot::hint_graph_to_dot(&graph));
+        }
+        _ => {
+            return Err(error::BackendError::BadRequest(format!(
+                "unknown command {command}, expected repair-sideconditions, check-hints, dot-tree or dot-hints"
+            )))
+        }
+    }
+    trx.commit().await?;
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    lib::derivation::sat::set_memory_limit();
 
     let db = match connect_db().await {
         Ok(con) => con,
         Err(err) => return error!("unable to connect to database: {err}"),
     };
 
+    // One-off maintenance instead of serving.
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Some((command, flags)) = args.split_first() {
+        match run_command(command, flags, &db).await {
+            Ok(()) => info!("{command} done"),
+            Err(err) => {
+                error!("{command} failed: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let origins = ["http://localhost:3000".parse().unwrap()];
 
     let app_state = AppState { db };
@@ -8,6 +8,9 @@ const injectedRtkApi = api.injectEndpoints({
         body: queryArg.createTreeRequest,
       }),
     }),
+    checkHints: build.query<CheckHintsApiResponse, CheckHintsApiArg>({
+      query: () => ({ url: `/api/admin/hints/check` }),
+    }),
     applyRule: build.mutation<ApplyRuleApiResponse, ApplyRuleApiArg>({
       query: (queryArg) => ({
         url: `/api/apply`,
@@ -15,6 +18,13 @@ const injectedRtkApi = api.injectEndpoints({
         body: queryArg.applyRuleParams,
       }),
     }),
+    applyForward: build.mutation<ApplyForwardApiResponse, ApplyForwardApiArg>({
+      query: (queryArg) => ({
+        url: `/api/apply/forward`,
+        method: "POST",
+        body: queryArg.forwardRuleParams,
+      }),
+    }),
     check: build.mutation<CheckApiResponse, CheckApiArg>({
       query: (queryArg) => ({
         url: `/api/check`,
@@ -45,6 +55,24 @@ const injectedRtkApi = api.injectEndpoints({
         body: queryArg.feedback,
       }),
     }),
+    getExerciseHintsDot: build.query<
+      GetExerciseHintsDotApiResponse,
+      GetExerciseHintsDotApiArg
+    >({
+      query: (queryArg) => ({ url: `/api/exercise/${queryArg.id}/hints/dot` }),
+    }),
+    getExerciseLatex: build.query<
+      GetExerciseLatexApiResponse,
+      GetExerciseLatexApiArg
+    >({
+      query: (queryArg) => ({ url: `/api/exercise/${queryArg.id}/latex` }),
+    }),
+    getExerciseSolution: build.query<
+      GetExerciseSolutionApiResponse,
+      GetExerciseSolutionApiArg
+    >({
+      query: (queryArg) => ({ url: `/api/exercise/${queryArg.id}/solution` }),
+    }),
     parse: build.mutation<ParseApiResponse, ParseApiArg>({
       query: (queryArg) => ({
         url: `/api/parse`,
@@ -55,11 +83,100 @@ const injectedRtkApi = api.injectEndpoints({
     allRules: build.query<AllRulesApiResponse, AllRulesApiArg>({
       query: () => ({ url: `/api/rules` }),
     }),
+    applicableRules: build.mutation<
+      ApplicableRulesApiResponse,
+      ApplicableRulesApiArg
+    >({
+      query: (queryArg) => ({
+        url: `/api/rules/applicable`,
+        method: "POST",
+        body: queryArg.applicableRulesParams,
+      }),
+    }),
+    createSession: build.mutation<
+      CreateSessionApiResponse,
+      CreateSessionApiArg
+    >({
+      query: (queryArg) => ({
+        url: `/api/session`,
+        method: "POST",
+        body: queryArg.createSessionRequest,
+      }),
+    }),
+    getSession: build.query<GetSessionApiResponse, GetSessionApiArg>({
+      query: (queryArg) => ({ url: `/api/session/${queryArg.id}` }),
+    }),
+    sessionApply: build.mutation<SessionApplyApiResponse, SessionApplyApiArg>({
+      query: (queryArg) => ({
+        url: `/api/session/${queryArg.id}/apply`,
+        method: "POST",
+        body: queryArg.sessionApplyParams,
+      }),
+    }),
+    completeSession: build.mutation<
+      CompleteSessionApiResponse,
+      CompleteSessionApiArg
+    >({
+      query: (queryArg) => ({
+        url: `/api/session/${queryArg.id}/complete`,
+        method: "POST",
+      }),
+    }),
+    sessionGoals: build.query<SessionGoalsApiResponse, SessionGoalsApiArg>({
+      query: (queryArg) => ({ url: `/api/session/${queryArg.id}/goals` }),
+    }),
+    sessionRedo: build.mutation<SessionRedoApiResponse, SessionRedoApiArg>({
+      query: (queryArg) => ({
+        url: `/api/session/${queryArg.id}/redo`,
+        method: "POST",
+      }),
+    }),
+    sessionUndo: build.mutation<SessionUndoApiResponse, SessionUndoApiArg>({
+      query: (queryArg) => ({
+        url: `/api/session/${queryArg.id}/undo`,
+        method: "POST",
+      }),
+    }),
     getTipp: build.mutation<GetTippApiResponse, GetTippApiArg>({
       query: (queryArg) => ({
         url: `/api/statement/hint`,
         method: "POST",
-        body: queryArg.statement,
+        body: queryArg.hintParams,
+      }),
+    }),
+    prove: build.mutation<ProveApiResponse, ProveApiArg>({
+      query: (queryArg) => ({
+        url: `/api/statement/prove`,
+        method: "POST",
+        body: queryArg.proveParams,
+      }),
+    }),
+    treeDot: build.mutation<TreeDotApiResponse, TreeDotApiArg>({
+      query: (queryArg) => ({
+        url: `/api/tree/dot`,
+        method: "POST",
+        body: queryArg.createTreeRequest,
+      }),
+    }),
+    treeLatex: build.mutation<TreeLatexApiResponse, TreeLatexApiArg>({
+      query: (queryArg) => ({
+        url: `/api/tree/latex`,
+        method: "POST",
+        body: queryArg.latexTreeRequest,
+      }),
+    }),
+    mergeTree: build.mutation<MergeTreeApiResponse, MergeTreeApiArg>({
+      query: (queryArg) => ({
+        url: `/api/tree/merge`,
+        method: "POST",
+        body: queryArg.mergeTreeRequest,
+      }),
+    }),
+    validate: build.mutation<ValidateApiResponse, ValidateApiArg>({
+      query: (queryArg) => ({
+        url: `/api/tree/validate`,
+        method: "POST",
+        body: queryArg.createTreeRequest,
       }),
     }),
   }),
@@ -70,11 +187,17 @@ export type AddTreeApiResponse = unknown;
 export type AddTreeApiArg = {
   createTreeRequest: CreateTreeRequest;
 };
-export type ApplyRuleApiResponse = /** status 200  */ Statement[];
+export type CheckHintsApiResponse = /** status 200  */ HintGraphReport;
+export type CheckHintsApiArg = void;
+export type ApplyRuleApiResponse = /** status 200  */ AppliedRule;
 export type ApplyRuleApiArg = {
   applyRuleParams: ApplyRuleParams;
 };
-export type CheckApiResponse = unknown;
+export type ApplyForwardApiResponse = /** status 200  */ Statement;
+export type ApplyForwardApiArg = {
+  forwardRuleParams: ForwardRuleParams;
+};
+export type CheckApiResponse = /** status 200  */ CheckResult;
 export type CheckApiArg = {
   createExerciseRequest: CreateExerciseRequest;
 };
@@ -93,15 +216,81 @@ export type PostFeedbackApiArg = {
   id: string;
   feedback: Feedback;
 };
-export type ParseApiResponse = /** status 200  */ Formula;
+export type GetExerciseHintsDotApiResponse = /** status 200  */ DotGraph;
+export type GetExerciseHintsDotApiArg = {
+  id: string;
+};
+export type GetExerciseLatexApiResponse = /** status 200  */ LatexStatement;
+export type GetExerciseLatexApiArg = {
+  id: string;
+};
+export type GetExerciseSolutionApiResponse =
+  /** status 200  */ ProofSearchResult;
+export type GetExerciseSolutionApiArg = {
+  id: string;
+};
+export type ParseApiResponse = /** status 200  */ ParsedFormula;
 export type ParseApiArg = {
   parseParams: ParseParams;
 };
 export type AllRulesApiResponse = /** status 200  */ DerivationRule[];
 export type AllRulesApiArg = void;
+export type ApplicableRulesApiResponse = /** status 200  */ AppliedRule[];
+export type ApplicableRulesApiArg = {
+  applicableRulesParams: ApplicableRulesParams;
+};
+export type CreateSessionApiResponse = /** status 200  */ ProofSession;
+export type CreateSessionApiArg = {
+  createSessionRequest: CreateSessionRequest;
+};
+export type GetSessionApiResponse = /** status 200  */ ProofSession;
+export type GetSessionApiArg = {
+  id: string;
+};
+export type SessionApplyApiResponse = /** status 200  */ ProofSession;
+export type SessionApplyApiArg = {
+  id: string;
+  sessionApplyParams: SessionApplyParams;
+};
+export type CompleteSessionApiResponse = /** status 200  */ ProofSession;
+export type CompleteSessionApiArg = {
+  id: string;
+};
+export type SessionGoalsApiResponse = /** status 200  */ Goal[];
+export type SessionGoalsApiArg = {
+  id: string;
+};
+export type SessionRedoApiResponse = /** status 200  */ ProofSession;
+export type SessionRedoApiArg = {
+  id: string;
+};
+export type SessionUndoApiResponse = /** status 200  */ ProofSession;
+export type SessionUndoApiArg = {
+  id: string;
+};
 export type GetTippApiResponse = /** status 200  */ Tipp[];
 export type GetTippApiArg = {
-  statement: Statement;
+  hintParams: HintParams;
+};
+export type ProveApiResponse = /** status 200  */ ProofSearchResult;
+export type ProveApiArg = {
+  proveParams: ProveParams;
+};
+export type TreeDotApiResponse = /** status 200  */ DotGraph;
+export type TreeDotApiArg = {
+  createTreeRequest: CreateTreeRequest;
+};
+export type TreeLatexApiResponse = /** status 200  */ LatexTree;
+export type TreeLatexApiArg = {
+  latexTreeRequest: LatexTreeRequest;
+};
+export type MergeTreeApiResponse = /** status 200  */ CreateTreeRequest;
+export type MergeTreeApiArg = {
+  mergeTreeRequest: MergeTreeRequest;
+};
+export type ValidateApiResponse = /** status 200  */ TreeValidation;
+export type ValidateApiArg = {
+  createTreeRequest: CreateTreeRequest;
 };
 export type Rules =
   | "Ax"
@@ -121,7 +310,12 @@ export type Rules =
   | "ExistsElim"
   | "ExistsIntro"
   | "AlphaExists"
-  | "AlphaForall";
+  | "AlphaForall"
+  | "EqIntro"
+  | "EqElim"
+  | "Raa"
+  | "Lem"
+  | "DoubleNegElim";
 export type Identifier =
   | {
       type: "Literal";
@@ -131,6 +325,18 @@ export type Identifier =
       type: "Element";
       value: string;
     };
+export type Term =
+  | {
+      type: "Element";
+      value: string;
+    }
+  | {
+      type: "Function";
+      value: {
+        args: Term[];
+        name: string;
+      };
+    };
 export type Formula =
   | {
       body: {
@@ -184,9 +390,16 @@ export type Formula =
   | {
       body: {
         identifier: Identifier;
-        identifiers: Identifier[];
+        identifiers: Term[];
       };
       type: "Predicate";
+    }
+  | {
+      body: {
+        lhs: Term;
+        rhs: Term;
+      };
+      type: "Eq";
     };
 export type Pair = {
   element: Identifier;
@@ -197,7 +410,7 @@ export type SideCondition = {
 };
 export type Statement = {
   formula: Formula;
-  lhs: Formula[];
+  lhs?: Formula[];
   sidecondition: SideCondition[];
 };
 export type Node = {
@@ -210,6 +423,19 @@ export type CreateTreeRequest = {
   nodes: Node[];
   root_id: string;
 };
+export type InvalidDerivation = {
+  message: string;
+  rule: Rules;
+  statement: string;
+};
+export type HintGraphReport = {
+  /** The number of checked derivations. */
+  checked: number;
+  /** The statements along every cycle of the graph. */
+  cycles: string[][];
+  invalid: InvalidDerivation[];
+  unreachable: string[];
+};
 export type FormulaMapping = {
   from: number;
   to: Formula;
@@ -218,16 +444,79 @@ export type ElementMapping = {
   from: string;
   to: string;
 };
+export type RuleIdentifier =
+  | {
+      type: "Formula";
+      value: number;
+    }
+  | {
+      type: "Element";
+      value: string;
+    }
+  | {
+      type: "Negation";
+      /** The negation of the formula `Formula(id)` stands for, e.g. `¬A` in `A ∨ ¬A`. */
+      value: number;
+    };
+export type AppliedRule = {
+  mapping: FormulaMapping[];
+  premisses: Statement[];
+  rule: Rules;
+  substitution: ElementMapping[];
+  unbound: RuleIdentifier[];
+};
 export type ApplyRuleParams = {
   mapping: FormulaMapping[];
   rule: Rules;
   statement: Statement;
   substitution: ElementMapping[];
 };
+export type ForwardRuleParams = {
+  /** The assumptions of the conclusion, only used by rules without premisses. */
+  context?: Formula[] | null;
+  mapping: FormulaMapping[];
+  /** Already derived statements, in the order of the premisses of the rule. */
+  premisses: Statement[];
+  rule: Rules;
+  substitution: ElementMapping[];
+};
+export type FunctionValue = {
+  args: string[];
+  value: string;
+};
+export type CounterModel = {
+  domain: string[];
+  elements: {
+    [key: string]: string;
+  };
+  functions: {
+    [key: string]: FunctionValue[];
+  };
+  literals: {
+    [key: string]: boolean;
+  };
+  predicates: {
+    [key: string]: string[][];
+  };
+};
+export type Verdict = "Valid" | "Invalid" | "Unknown";
+export type CheckResult = {
+  countermodel?: null | CounterModel;
+  intuitionistic?: null | Verdict;
+  /** Why the solver could not decide, e.g. a timeout. */
+  reason?: string | null;
+  valid: boolean;
+  verdict: Verdict;
+};
 export type CreateExerciseRequest = {
+  /** Classical exercises may be solved with the classical rules (RAA, LEM, ¬¬E). */
+  classical?: boolean;
   statement: Statement;
+  /** Time limit for the validity check, capped by the server. */
+  timeout_ms?: number | null;
 };
 export type Exercise = {
+  classical: boolean;
   difficulty: number;
   dislikes: number;
   exercise: Statement;
@@ -239,18 +528,38 @@ export type Feedback = {
   difficulty?: number | null;
   like: boolean;
 };
+export type DotGraph = {
+  dot: string;
+};
+export type LatexStatement = {
+  data: {
+    formula: Formula;
+    lhs?: Formula[];
+    sidecondition: SideCondition[];
+  };
+  latex: string;
+};
+export type ProofSearchResult = {
+  found: boolean;
+  tree?: null | CreateTreeRequest;
+};
+export type Signature = {
+  elements: string[];
+  functions: {
+    [key: string]: number;
+  };
+  literals: string[];
+  predicates: {
+    [key: string]: number;
+  };
+};
+export type ParsedFormula = {
+  formula: Formula;
+  signature: Signature;
+};
 export type ParseParams = {
   formula: string;
 };
-export type RuleIdentifier =
-  | {
-      type: "Formula";
-      value: number;
-    }
-  | {
-      type: "Element";
-      value: string;
-    };
 export type RuleFormula =
   | {
       body: RuleIdentifier;
@@ -308,6 +617,13 @@ export type RuleFormula =
         rhs: RuleIdentifier;
       };
       type: "Substitution";
+    }
+  | {
+      body: {
+        lhs: RuleIdentifier;
+        rhs: RuleIdentifier;
+      };
+      type: "Eq";
     };
 export type RuleStatement = {
   formula: RuleFormula;
@@ -318,19 +634,109 @@ export type DerivationRule = {
   name: Rules;
   premises: RuleStatement[];
 };
+export type ApplicableRulesParams = {
+  classical?: boolean;
+  statement: Statement;
+};
+export type Goal = {
+  name: string;
+  statement: Statement;
+};
+export type ProofSession = {
+  can_redo: boolean;
+  can_undo: boolean;
+  completed: boolean;
+  exercise_id: string;
+  goals: Goal[];
+  id: string;
+  statement: Statement;
+  /** The rules applied so far, the open goals are not part of the tree. */
+  tree: CreateTreeRequest;
+};
+export type CreateSessionRequest = {
+  exercise_id: string;
+};
+export type SessionApplyParams = {
+  /** The open goal the rule is applied to. */
+  goal: string;
+  mapping: FormulaMapping[];
+  rule: Rules;
+  substitution: ElementMapping[];
+};
 export type Tipp = {
   premisses: Statement[];
   rule: Rules;
+  /** The number of rules of the shortest known derivation starting with the rule. */
+  size: number;
+  tree?: null | CreateTreeRequest;
+  /** How often the derivation was submitted. */
+  uses: number;
+};
+export type HintLevel = "Rule" | "Premisses" | "Tree";
+export type HintParams = Statement & {
+  classical?: boolean;
+  level?: HintLevel;
+};
+export type ProveParams = {
+  classical?: boolean;
+  max_depth?: number | null;
+  statement: Statement;
+  timeout_ms?: number | null;
+};
+export type LatexTree = {
+  document: string;
+  proof: string;
+};
+export type LatexStyle = "Bussproofs" | "Ebproof";
+export type LatexTreeRequest = {
+  style?: LatexStyle;
+  tree: CreateTreeRequest;
+};
+export type MergeTreeRequest = {
+  /** Goals without a rule, each is closed by a node of another fragment. */
+  goals: Goal[];
+  nodes: Node[];
+  root_id: string;
+};
+export type NodeError = {
+  actual: string[];
+  expected: string[];
+  message: string;
+  node: string;
+  rule?: null | Rules;
+  side_condition?: string | null;
+};
+export type TreeValidation = {
+  errors: NodeError[];
+  valid: boolean;
 };
 export const {
   useAddTreeMutation,
+  useCheckHintsQuery,
   useApplyRuleMutation,
+  useApplyForwardMutation,
   useCheckMutation,
   useGetExercisesQuery,
   useCreateExerciseMutation,
   useGetExerciseQuery,
   usePostFeedbackMutation,
+  useGetExerciseHintsDotQuery,
+  useGetExerciseLatexQuery,
+  useGetExerciseSolutionQuery,
   useParseMutation,
   useAllRulesQuery,
+  useApplicableRulesMutation,
+  useCreateSessionMutation,
+  useGetSessionQuery,
+  useSessionApplyMutation,
+  useCompleteSessionMutation,
+  useSessionGoalsQuery,
+  useSessionRedoMutation,
+  useSessionUndoMutation,
   useGetTippMutation,
+  useProveMutation,
+  useTreeDotMutation,
+  useTreeLatexMutation,
+  useMergeTreeMutation,
+  useValidateMutation,
 } = injectedRtkApi;
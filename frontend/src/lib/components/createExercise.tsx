@@ -36,6 +36,7 @@ import {
   Formula as FormulaType,
   Identifier as IdentifierType,
   SideCondition as SideConditionType,
+  Signature,
   Statement as StatementType,
   useCheckMutation,
   useCreateExerciseMutation,
@@ -49,6 +50,25 @@ import Statement from "./statement";
 import { getAllPlaceholders } from "../utils/formula";
 import { Identifier } from "./formula/formulaParts";
 
+const signatureText = (signature: Signature) => {
+  const groups: [string, string[]][] = [
+    [
+      "Predicates",
+      Object.entries(signature.predicates).map(([p, n]) => `${p}/${n}`),
+    ],
+    [
+      "Functions",
+      Object.entries(signature.functions).map(([f, n]) => `${f}/${n}`),
+    ],
+    ["Literals", signature.literals],
+    ["Elements", signature.elements],
+  ];
+  return groups
+    .filter(([_, names]) => names.length > 0)
+    .map(([kind, names]) => `${kind}: ${names.join(", ")}`)
+    .join(" \u00B7 ");
+};
+
 const CreateExerciseForm = () => {
   const [opened, { open, close }] = useDisclosure(false);
 
@@ -79,6 +99,7 @@ const CreateExerciseForm = () => {
     undefined,
   );
   const [parseError, setParseError] = useState<string | undefined>(undefined);
+  const [signature, setSignature] = useState<Signature | undefined>(undefined);
 
   // Sideconditions
   const [ScElement, setScElement] = useState<IdentifierType | undefined>(
@@ -110,7 +131,7 @@ const CreateExerciseForm = () => {
             },
           },
         }).unwrap();
-        setIsTautology(result as boolean);
+        setIsTautology(result.valid);
       } catch (error: any) {
         console.error(error.data);
       }
@@ -163,17 +184,23 @@ const CreateExerciseForm = () => {
       lhsFormulaHandler(undefined);
     }
     try {
-      let result = await parseFormula({
+      const result = await parseFormula({
         parseParams: { formula: formula },
       }).unwrap();
       if (lhs) {
-        lhsFormulaHandler(result);
+        lhsFormulaHandler(result.formula);
       } else {
-        rhsFormulaHandler(result);
+        rhsFormulaHandler(result.formula);
       }
+      setSignature(result.signature);
       setParseError(undefined);
     } catch (error: any) {
-      setParseError(error.data);
+      setSignature(undefined);
+      setParseError(
+        typeof error.data === "string"
+          ? `\n${error.data}`
+          : (error.data as { message?: string })?.message,
+      );
       if (lhs) {
         lhsFormulaHandler(undefined);
       } else {
@@ -264,11 +291,21 @@ const CreateExerciseForm = () => {
                 {lhsFormula && (
                   <Stack align="center" justify="center" h={125} p={0} m={0}>
                     <Formula formula={lhsFormula} />
+                    {signature && (
+                      <Text size="xs" c={"dimmed"}>
+                        {signatureText(signature)}
+                      </Text>
+                    )}
                   </Stack>
                 )}
                 {rhsFormula && (
                   <Stack align="center" justify="center" h={125} p={0} m={0}>
                     <Formula formula={rhsFormula} />
+                    {signature && (
+                      <Text size="xs" c={"dimmed"}>
+                        {signatureText(signature)}
+                      </Text>
+                    )}
                   </Stack>
                 )}
               </Card.Section>
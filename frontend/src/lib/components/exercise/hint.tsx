@@ -1,4 +1,6 @@
 import {
+  HintLevel,
+  Node as NodeType,
   Rules,
   Statement as StatementType,
   Tipp,
@@ -15,6 +17,7 @@ import {
   Group,
   Menu,
   Modal,
+  SegmentedControl,
   Stack,
   Text,
 } from "@mantine/core";
@@ -70,6 +73,45 @@ const DerivationStep = ({
   );
 };
 
+type HintTreeProps = {
+  nodes: NodeType[];
+  name: string;
+};
+
+const HintTree = ({ nodes, name }: HintTreeProps) => {
+  const node = nodes.find((n) => n.name == name);
+  if (!node) {
+    return null;
+  }
+  return (
+    <Stack gap={0}>
+      <Flex gap={"xl"} justify="center" align="flex-end">
+        {node.premisses.map((premiss) => (
+          <HintTree nodes={nodes} name={premiss} key={premiss} />
+        ))}
+      </Flex>
+
+      <Group justify="center" align="start">
+        <Stack gap={0}>
+          <Divider
+            style={{ borderColor: "currentColor" }}
+            mb={8}
+            w={"100%"}
+          ></Divider>
+          <Flex justify={"center"}>
+            <Statement statement={node.statement} />
+          </Flex>
+        </Stack>
+        <Group pl={-10} mt={-5} gap={15}>
+          <Box className="katex">
+            <RuleName name={node.rule} />
+          </Box>
+        </Group>
+      </Group>
+    </Stack>
+  );
+};
+
 type HintProps = {
   statement: StatementType;
   opened: boolean;
@@ -79,14 +121,17 @@ type HintProps = {
 const Hint = ({ statement, opened, close }: HintProps) => {
   const [getHint, refetch] = useGetTippMutation();
   const [hints, hintsHandler] = useListState<Tipp>([]);
+  const [level, setLevel] = useState<HintLevel>("Rule");
 
   useEffect(() => {
     loadHint();
-  }, [opened]);
+  }, [opened, level]);
 
   const loadHint = async () => {
     try {
-      let result = await getHint({ statement: statement }).unwrap();
+      const result = await getHint({
+        hintParams: { ...statement, level },
+      }).unwrap();
       hintsHandler.setState(result);
     } catch (error) {
       console.log(error);
@@ -102,6 +147,16 @@ const Hint = ({ statement, opened, close }: HintProps) => {
         size="xl"
         position="right"
       >
+        <SegmentedControl
+          mb="md"
+          value={level}
+          onChange={(value) => setLevel(value as HintLevel)}
+          data={[
+            { label: "Rule", value: "Rule" },
+            { label: "Premisses", value: "Premisses" },
+            { label: "Derivation", value: "Tree" },
+          ]}
+        />
         {hints.length == 0 && <Text>No Hint available</Text>}
 
         {hints && (
@@ -109,15 +164,31 @@ const Hint = ({ statement, opened, close }: HintProps) => {
             {hints.map((hint, i) => (
               <Accordion.Item key={i} value={i.toString()}>
                 <Accordion.Control>
-                  <Box className="katex">{getRuleByName(hint.rule)}</Box>
+                  <Group justify="space-between" pr="md">
+                    <Box className="katex">{getRuleByName(hint.rule)}</Box>
+                    {!!hint.uses && (
+                      <Text size="xs" c="dimmed">
+                        used {hint.uses} {hint.uses == 1 ? "time" : "times"}
+                      </Text>
+                    )}
+                  </Group>
                 </Accordion.Control>
-                <Accordion.Panel>
-                  <DerivationStep
-                    statement={statement}
-                    premisses={hint.premisses}
-                    rule={hint.rule}
-                  />
-                </Accordion.Panel>
+                {level != "Rule" && (
+                  <Accordion.Panel>
+                    {hint.tree ? (
+                      <HintTree
+                        nodes={hint.tree.nodes}
+                        name={hint.tree.root_id}
+                      />
+                    ) : (
+                      <DerivationStep
+                        statement={statement}
+                        premisses={hint.premisses}
+                        rule={hint.rule}
+                      />
+                    )}
+                  </Accordion.Panel>
+                )}
               </Accordion.Item>
             ))}
           </Accordion>
@@ -181,14 +181,13 @@ const Matcher = () => {
   const handleCustomFormula = async () => {
     if (customFormula) {
       try {
-        let result = await parseFormula({
+        const result = await parseFormula({
           parseParams: { formula: customFormula },
         }).unwrap();
-        const f = result as FormulaType;
-        handleClick(f);
+        handleClick(result.formula);
         setParseError("");
       } catch (error: any) {
-        setParseError(error.data as string);
+        setParseError((error.data as { message?: string })?.message ?? "");
       }
     }
   };
@@ -196,7 +195,7 @@ const Matcher = () => {
   const applyRule = async () => {
     if (rule_name && current_node) {
       try {
-        const result = await applyRuleRequest({
+        const applied = await applyRuleRequest({
           applyRuleParams: {
             rule: rule_name,
             statement: {
@@ -208,8 +207,21 @@ const Matcher = () => {
             substitution: elementMatcher,
           },
         }).unwrap();
+        if (applied.unbound.length > 0) {
+          // Keep the inferred mappings and ask for the missing ones.
+          formulaMatcherHandler.setState(applied.mapping);
+          elementMatcherHandler.setState(applied.substitution);
+          const index = allIdentifiers.findIndex((ident) =>
+            applied.unbound.some(
+              (u) => u.type === ident.type && u.value === ident.value,
+            ),
+          );
+          setCounter(index >= 0 ? index : undefined);
+          setApplyError(undefined);
+          return;
+        }
         const new_uuids: UUID[] = [];
-        const new_nodes = result.map((n) => {
+        const new_nodes = applied.premisses.map((n) => {
           const id = uuidv4() as UUID;
           new_uuids.push(id);
           return {
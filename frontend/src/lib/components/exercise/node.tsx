@@ -116,7 +116,7 @@ const Node = ({ node, all_nodes, side_con }: NodeProps) => {
           },
         },
       }).unwrap();
-      setIsGood(result as boolean);
+      setIsGood(result.valid);
     } catch (error) {
       setIsGood(undefined);
     }
@@ -3,6 +3,7 @@ import { useMemo } from "react";
 import { Formula as FormulaType } from "../../api";
 import {
   And,
+  Eq,
   Exists,
   False,
   Forall,
@@ -61,6 +62,8 @@ const Formula = ({
         return (
           <Predicate formula={formula} click={click} textColor={textColor} />
         );
+      case "Eq":
+        return <Eq formula={formula} click={click} textColor={textColor} />;
     }
   }, [formula, click, textColor]);
 
@@ -5,6 +5,7 @@ import {
   useMantineColorScheme,
 } from "@mantine/core";
 import { useHover } from "@mantine/hooks";
+import { Term as TermType } from "../../api";
 import Formula, { FormulaProps } from "./formula";
 
 const getStyle = (hovered: boolean, colorScheme: MantineColorScheme) => {
@@ -172,6 +173,25 @@ const Exists = ({ formula, click, textColor }: FormulaProps<"Exists">) => {
   );
 };
 
+const Term = ({ term, textColor }: { term: TermType; textColor?: string }) => {
+  if (term.type === "Element") {
+    return <Text c={textColor}>{term.value}</Text>;
+  }
+  return (
+    <Group gap={1}>
+      <Text c={textColor}>{term.value.name}</Text>
+      <Text c={textColor}>(</Text>
+      {term.value.args.map((arg, index) => (
+        <Group key={index} gap={0}>
+          {index > 0 && <Text c={textColor}>,</Text>}{" "}
+          <Term term={arg} textColor={textColor} />
+        </Group>
+      ))}
+      <Text c={textColor}>)</Text>
+    </Group>
+  );
+};
+
 const Predicate = ({
   formula,
   click,
@@ -185,35 +205,34 @@ const Predicate = ({
         {formula.body.identifier.value}
       </Text>
       <Text c={textColor}>(</Text>
-      {formula.body.identifiers.map((term, index) => {
-        if (index > 0) {
-          return (
-            <Group key={index} gap={0}>
-              <Text c={textColor}>,</Text>{" "}
-              <Formula
-                formula={{ type: "Ident", body: term }}
-                click={click}
-                textColor={textColor}
-              />
-            </Group>
-          );
-        }
-        return (
-          <Formula
-            formula={{ type: "Ident", body: term }}
-            key={index}
-            click={click}
-            textColor={textColor}
-          />
-        );
-      })}
+      {formula.body.identifiers.map((term, index) => (
+        <Group key={index} gap={0}>
+          {index > 0 && <Text c={textColor}>,</Text>}{" "}
+          <Term term={term} textColor={textColor} />
+        </Group>
+      ))}
       <Text c={textColor}>)</Text>
     </Group>
   );
 };
 
+const Eq = ({ formula, textColor }: FormulaProps<"Eq">) => {
+  const { hovered, ref } = useHover();
+  const { colorScheme } = useMantineColorScheme();
+  return (
+    <Group gap={1} style={getStyle(hovered, colorScheme)}>
+      <Term term={formula.body.lhs} textColor={textColor} />
+      <Text px={3} ref={ref} c={textColor}>
+        =
+      </Text>
+      <Term term={formula.body.rhs} textColor={textColor} />
+    </Group>
+  );
+};
+
 export {
   And,
+  Eq,
   Exists,
   False,
   Forall,
@@ -223,5 +242,6 @@ export {
   Not,
   Or,
   Predicate,
+  Term,
   True,
 };
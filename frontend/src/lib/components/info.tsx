@@ -84,8 +84,17 @@ const Info = () => {
             </List.Item>
             <List.Item>
               <Text>
-                Use lowercase letters for variables and predicates only. Use
-                uppercase letters for literals.
+                Without brackets, not binds strongest, followed by and, or and
+                finally -{">"}. A -{">"} B -{">"} C means A -{">"} (B -{">"}
+                C). A quantifier covers everything to its right, forall_x p(x)
+                -{">"} q(x) means forall_x (p(x) -{">"} q(x)).
+              </Text>
+            </List.Item>
+            <List.Item>
+              <Text>
+                Variables and predicates start with a lowercase letter, literals
+                with an uppercase letter. Names may continue with letters,
+                digits, _ and &apos;, e.g. P1, x_2 or loves(x, y).
               </Text>
             </List.Item>
             <List.Item>
@@ -15,14 +15,15 @@ const Statement = ({
   textColor,
   showSideCondition = false,
 }: StatementProps) => {
+  const lhs = statement.lhs ?? [];
   return (
     <Group className="katex" gap={0}>
-      {statement.lhs.length == 0 && <Text mb={-1}>{"\u2205"}</Text>}
-      {statement.lhs.map((a, i) => {
+      {lhs.length == 0 && <Text mb={-1}>{"\u2205"}</Text>}
+      {lhs.map((a, i) => {
         return (
           <Group key={i} gap={1}>
             <Formula formula={a} click={click} textColor={textColor} />
-            {i < statement.lhs.length - 1 && (
+            {i < lhs.length - 1 && (
               <Text pe={3} c={textColor}>
                 ,
               </Text>
@@ -1,4 +1,4 @@
-import { Formula, Rules } from "../api";
+import { Formula, Rules, Term } from "../api";
 import { NodeType } from "../components/exercise/node";
 import { getTypstRuleByName } from "./rule";
 
@@ -34,7 +34,8 @@ function exportSubformula(
   footnoteNumber: number,
 ): [string, Array<[number, string]>] {
   let name = getTypstRuleByName(node.rule as Rules, footnoteNumber);
-  const lhs = node.statement.lhs.map(formulaToTypst).join(", ") || "emptyset";
+  const lhs =
+    (node.statement.lhs ?? []).map(formulaToTypst).join(", ") || "emptyset";
   const current = formulaToTypst(node.statement.formula);
   let currentFootnoteNumber = footnoteNumber;
   const footnotes: Array<[number, string]> = [];
@@ -95,10 +96,21 @@ function formulaToTypst(formula: Formula): string {
     case "Exists":
       return `exists ${formulaToTypst({ type: "Ident", body: formula.body.identifier })}. ${formulaToTypst(formula.body.formula)}`;
     case "Predicate": {
-      const vars = formula.body.identifiers.map((id) => id.value).join(", ");
+      const vars = formula.body.identifiers.map(termToTypst).join(", ");
       return `${formula.body.identifier.value}(${vars})`;
     }
+    case "Eq":
+      return `${termToTypst(formula.body.lhs)} = ${termToTypst(formula.body.rhs)}`;
     default:
       return "";
   }
 }
+
+function termToTypst(term: Term): string {
+  switch (term.type) {
+    case "Element":
+      return term.value;
+    case "Function":
+      return `${term.value.name}(${term.value.args.map(termToTypst).join(", ")})`;
+  }
+}